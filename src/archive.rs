@@ -0,0 +1,252 @@
+use std::{
+    env, fs, io,
+    path::Path,
+    process::{self, Command, Stdio},
+};
+
+use anyhow::{anyhow, Result};
+
+const BLOCK_SIZE: usize = 512;
+
+/// Returns whether `bytes` starts with a POSIX (ustar) tar header.
+pub fn is_tar(bytes: &[u8]) -> bool {
+    bytes.len() >= BLOCK_SIZE && &bytes[257..262] == b"ustar"
+}
+
+/// Returns whether `bytes` starts with the signature of a zip archive,
+/// either of its first member or of an empty archive.
+pub fn is_zip(bytes: &[u8]) -> bool {
+    bytes.starts_with(b"PK\x03\x04") || bytes.starts_with(b"PK\x05\x06")
+}
+
+/// Matches `name` against a glob `pattern`,
+/// where `*` matches any sequence of characters and `?` matches any single character.
+pub fn glob_match(pattern: &str, name: &str) -> bool {
+    let pattern = pattern.chars().collect::<Vec<_>>();
+    let name = name.chars().collect::<Vec<_>>();
+
+    let (mut p, mut n) = (0, 0);
+    // Position of the last `*` in the pattern and the name position it was tried at.
+    let mut backtrack = None;
+    while n < name.len() {
+        match pattern.get(p) {
+            Some('*') => {
+                backtrack = Some((p, n));
+                p += 1;
+            }
+            Some(c) if *c == '?' || *c == name[n] => {
+                p += 1;
+                n += 1;
+            }
+            _ => match backtrack {
+                Some((star, tried)) => {
+                    p = star + 1;
+                    n = tried + 1;
+                    backtrack = Some((star, tried + 1));
+                }
+                None => return false,
+            },
+        }
+    }
+    pattern[p..].iter().all(|c| *c == '*')
+}
+
+fn parse_octal(field: &[u8]) -> Result<usize> {
+    let digits = String::from_utf8_lossy(field);
+    let digits = digits.trim_matches(|c: char| c == '\0' || c == ' ');
+    if digits.is_empty() {
+        return Ok(0);
+    }
+    usize::from_str_radix(digits, 8).map_err(|_| anyhow!("Invalid tar header: '{}'", digits))
+}
+
+fn parse_name(field: &[u8]) -> String {
+    let end = field.iter().position(|b| *b == 0).unwrap_or(field.len());
+    String::from_utf8_lossy(&field[..end]).to_string()
+}
+
+/// Extracts the regular files in a tar archive whose names match `pattern`,
+/// returning their names and contents in archive order.
+pub fn read_tar(bytes: &[u8], pattern: &str) -> Result<Vec<(String, String)>> {
+    let mut ret = Vec::new();
+    let mut offset = 0;
+    // Name set by a preceding GNU long name entry.
+    let mut long_name = None;
+
+    while offset + BLOCK_SIZE <= bytes.len() {
+        let header = &bytes[offset..offset + BLOCK_SIZE];
+        if header.iter().all(|b| *b == 0) {
+            break;
+        }
+
+        let size = parse_octal(&header[124..136])?;
+        let start = offset + BLOCK_SIZE;
+        let data = bytes
+            .get(start..start + size)
+            .ok_or_else(|| anyhow!("Tar archive is truncated"))?;
+        offset = start + size.div_ceil(BLOCK_SIZE) * BLOCK_SIZE;
+
+        let name = match long_name.take() {
+            Some(name) => name,
+            None => {
+                let prefix = parse_name(&header[345..500]);
+                let name = parse_name(&header[0..100]);
+                if prefix.is_empty() {
+                    name
+                } else {
+                    format!("{}/{}", prefix, name)
+                }
+            }
+        };
+
+        match header[156] {
+            b'L' => long_name = Some(parse_name(data)),
+            b'0' | 0 if glob_match(pattern, &name) => {
+                let contents = String::from_utf8(data.to_vec())
+                    .map_err(|_| anyhow!("{} in tar archive is not valid UTF-8", name))?;
+                ret.push((name, contents));
+            }
+            _ => {}
+        }
+    }
+    Ok(ret)
+}
+
+/// Extracts the files in a zip archive whose names match `pattern`
+/// with the `unzip` binary on PATH, returning their names and contents in archive order.
+/// unzip needs to seek in the archive, so it is written to a temporary file first.
+pub fn read_zip(bytes: &[u8], pattern: &str) -> Result<Vec<(String, String)>> {
+    let path = env::temp_dir().join(format!("jnv-{}.zip", process::id()));
+    fs::write(&path, bytes)?;
+    let ret = read_zip_file(&path, pattern);
+    let _ = fs::remove_file(&path);
+    ret
+}
+
+fn read_zip_file(path: &Path, pattern: &str) -> Result<Vec<(String, String)>> {
+    let names = unzip("-Z1", path, None)?;
+    String::from_utf8_lossy(&names)
+        .lines()
+        .filter(|name| !name.ends_with('/') && glob_match(pattern, name))
+        .map(|name| {
+            let contents = unzip("-p", path, Some(&escape_wildcards(name)))?;
+            let contents = String::from_utf8(contents)
+                .map_err(|_| anyhow!("{} in zip archive is not valid UTF-8", name))?;
+            Ok((name.to_string(), contents))
+        })
+        .collect()
+}
+
+/// Escapes the characters that unzip would otherwise take as wildcards in a member name.
+fn escape_wildcards(name: &str) -> String {
+    let mut escaped = String::new();
+    for c in name.chars() {
+        if matches!(c, '\\' | '*' | '?' | '[') {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+/// Runs `unzip` with `option` on the archive at `path`, limited to `member` if given,
+/// returning what it printed.
+fn unzip(option: &str, path: &Path, member: Option<&str>) -> Result<Vec<u8>> {
+    let output = Command::new("unzip")
+        .arg(option)
+        .arg(path)
+        .args(member)
+        .stdin(Stdio::null())
+        .output()
+        .map_err(|e| match e.kind() {
+            io::ErrorKind::NotFound => anyhow!(
+                "Input is a zip archive, but the unzip command needed to extract it \
+                 was not found on PATH. Install unzip, or extract the input before \
+                 passing it to jnv"
+            ),
+            _ => anyhow!("Failed to run unzip: {}", e),
+        })?;
+    if !output.status.success() {
+        return Err(anyhow!(
+            "Failed to extract zip input: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+    Ok(output.stdout)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    mod glob_match {
+        use super::*;
+
+        #[test]
+        fn test_wildcards() {
+            assert!(glob_match("*.json", "data/a.json"));
+            assert!(glob_match("a?c", "abc"));
+            assert!(glob_match("*", ""));
+            assert!(glob_match("*a*b", "xxaxxb"));
+        }
+
+        #[test]
+        fn test_no_match() {
+            assert!(!glob_match("*.json", "a.jsonl"));
+            assert!(!glob_match("a?c", "ac"));
+        }
+    }
+
+    mod escape_wildcards {
+        use super::*;
+
+        #[test]
+        fn test() {
+            assert_eq!("data/a.json", escape_wildcards("data/a.json"));
+            assert_eq!(r"a\[1]\*\?\\.json", escape_wildcards(r"a[1]*?\.json"));
+        }
+    }
+
+    mod read_tar {
+        use super::*;
+
+        fn entry(name: &str, contents: &str) -> Vec<u8> {
+            let mut header = vec![0; BLOCK_SIZE];
+            header[..name.len()].copy_from_slice(name.as_bytes());
+            header[124..135].copy_from_slice(format!("{:011o}", contents.len()).as_bytes());
+            header[156] = b'0';
+            header[257..262].copy_from_slice(b"ustar");
+
+            let mut data = contents.as_bytes().to_vec();
+            data.resize(contents.len().div_ceil(BLOCK_SIZE) * BLOCK_SIZE, 0);
+            [header, data].concat()
+        }
+
+        #[test]
+        fn test_matching_members() {
+            let archive = [
+                entry("a.json", r#"{"a": 1}"#),
+                entry("README.md", "# readme"),
+                entry("b.json", r#"{"b": 2}"#),
+                vec![0; BLOCK_SIZE * 2],
+            ]
+            .concat();
+
+            assert!(is_tar(&archive));
+            assert_eq!(
+                vec![
+                    ("a.json".to_string(), r#"{"a": 1}"#.to_string()),
+                    ("b.json".to_string(), r#"{"b": 2}"#.to_string()),
+                ],
+                read_tar(&archive, "*.json").unwrap()
+            );
+        }
+
+        #[test]
+        fn test_truncated() {
+            let mut archive = entry("a.json", r#"{"a": 1}"#);
+            archive.truncate(BLOCK_SIZE + 2);
+            assert!(read_tar(&archive, "*").is_err());
+        }
+    }
+}
@@ -0,0 +1,69 @@
+use std::process::{Command, Stdio};
+
+use anyhow::Result;
+
+/// Returns whether `value` looks like a URL that can be opened in a web browser.
+pub fn is_url(value: &str) -> bool {
+    (value.starts_with("http://") || value.starts_with("https://"))
+        && !value.chars().any(|c| c.is_whitespace() || c.is_control())
+}
+
+/// Opens `url` in the default web browser without waiting for it.
+pub fn open(url: &str) -> Result<()> {
+    command(url)
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()?;
+    Ok(())
+}
+
+/// Returns the command opening `url`, passed as a single argument.
+/// On Windows this avoids `cmd /C start`, which would run anything after `&` or `|` in it.
+fn command(url: &str) -> Command {
+    let mut command = if cfg!(target_os = "windows") {
+        let mut command = Command::new("rundll32");
+        command.arg("url.dll,FileProtocolHandler");
+        command
+    } else if cfg!(target_os = "macos") {
+        Command::new("open")
+    } else {
+        Command::new("xdg-open")
+    };
+    command.arg(url);
+    command
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    mod is_url {
+        use super::*;
+
+        #[test]
+        fn test_url() {
+            assert!(is_url("https://example.com/a?b=c"));
+            assert!(is_url("http://localhost:8080"));
+        }
+
+        #[test]
+        fn test_not_url() {
+            assert!(!is_url("example.com"));
+            assert!(!is_url("file:///etc/passwd"));
+            assert!(!is_url("https://example.com/a b"));
+        }
+    }
+
+    mod command {
+        use super::*;
+
+        #[test]
+        fn test_shell_metacharacters() {
+            let url = "https://example.com/?a=1&calc|calc^&\"calc\"";
+            let command = command(url);
+            assert_ne!("cmd", command.get_program());
+            assert_eq!(Some(url.as_ref()), command.get_args().last());
+        }
+    }
+}
@@ -0,0 +1,57 @@
+use std::{fs::OpenOptions, io::Write, sync::mpsc, thread, time::Duration};
+
+use anyhow::{anyhow, Result};
+use base64::{engine::general_purpose::STANDARD, Engine};
+
+/// The controlling terminal, which the escape sequence is written to.
+#[cfg(unix)]
+const TERMINAL: &str = "/dev/tty";
+#[cfg(windows)]
+const TERMINAL: &str = "CONOUT$";
+
+/// Copies `text` to the system clipboard through the terminal
+/// with the OSC 52 escape sequence, which also works over SSH.
+/// Whether the terminal honors the sequence cannot be detected.
+/// Fails if writing does not finish within `timeout`,
+/// e.g. when the terminal stops reading while a clipboard manager is busy.
+pub fn copy(text: &str, timeout: Duration) -> Result<()> {
+    let sequence = format!("\x1b]52;c;{}\x07", STANDARD.encode(text));
+    // A handle of its own, so that a stuck write never holds the lock on stdout
+    // that the renderer needs, even after timing out.
+    let mut tty = OpenOptions::new()
+        .write(true)
+        .open(TERMINAL)
+        .map_err(|e| anyhow!("failed to open {}: {}", TERMINAL, e))?;
+    let (tx, rx) = mpsc::channel();
+    thread::spawn(move || {
+        let result = write!(tty, "{}", sequence).and_then(|_| tty.flush());
+        let _ = tx.send(result);
+    });
+    match rx.recv_timeout(timeout) {
+        Ok(result) => Ok(result?),
+        Err(_) => Err(anyhow!("timed out after {}ms", timeout.as_millis())),
+    }
+}
+
+/// Encodes `data` as a base64 data URI with the given MIME type,
+/// e.g. `data:application/json;base64,e30=` for `{}`.
+pub fn data_uri(mime: &str, data: &str) -> String {
+    format!("data:{};base64,{}", mime, STANDARD.encode(data))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    mod data_uri {
+        use super::*;
+
+        #[test]
+        fn test() {
+            assert_eq!(
+                "data:application/json;base64,eyJhIjoxfQ==",
+                data_uri("application/json", r#"{"a":1}"#)
+            );
+        }
+    }
+}
@@ -0,0 +1,145 @@
+use anyhow::{anyhow, Result};
+use promkit::serde_json;
+
+/// Splits CSV `text` into records of fields, following RFC 4180:
+/// fields are separated by commas and records by newlines (LF or CRLF),
+/// and fields enclosed in double quotes may contain commas, newlines
+/// and double quotes written twice. Blank lines are skipped.
+pub fn records(text: &str) -> Result<Vec<Vec<String>>> {
+    let mut ret = Vec::new();
+    let mut record = Vec::new();
+    let mut field = String::new();
+    // Whether the current field is enclosed in quotes, and its closing quote was not read yet.
+    let mut quoted = false;
+    let mut line = 1;
+    let mut chars = text.chars().peekable();
+    while let Some(ch) = chars.next() {
+        match ch {
+            '"' if quoted => {
+                if chars.peek() == Some(&'"') {
+                    chars.next();
+                    field.push('"');
+                } else {
+                    quoted = false;
+                }
+            }
+            '"' if field.is_empty() => quoted = true,
+            ch if quoted => {
+                if ch == '\n' {
+                    line += 1;
+                }
+                field.push(ch);
+            }
+            ',' => record.push(std::mem::take(&mut field)),
+            '\r' if chars.peek() == Some(&'\n') => {}
+            '\n' => {
+                line += 1;
+                if !record.is_empty() || !field.is_empty() {
+                    record.push(std::mem::take(&mut field));
+                    ret.push(std::mem::take(&mut record));
+                }
+            }
+            ch => field.push(ch),
+        }
+    }
+    if quoted {
+        return Err(anyhow!("Unterminated quoted field in CSV at line {}", line));
+    }
+    if !record.is_empty() || !field.is_empty() {
+        record.push(field);
+        ret.push(record);
+    }
+    Ok(ret)
+}
+
+/// Converts CSV `text` into one JSON object per record after the header,
+/// keyed by the fields of the header, keeping at most `limit_length` of them,
+/// and tells whether that left records out.
+/// Values are kept as strings; fields missing at the end of a record are `null`.
+pub fn to_json(text: &str, limit_length: Option<usize>) -> Result<(Vec<serde_json::Value>, bool)> {
+    let mut records = records(text)?.into_iter();
+    let Some(header) = records.next() else {
+        return Ok((Vec::new(), false));
+    };
+    let values = records
+        .by_ref()
+        .take(limit_length.unwrap_or(usize::MAX))
+        .enumerate()
+        .map(|(i, record)| {
+            if record.len() > header.len() {
+                return Err(anyhow!(
+                    "CSV record {} has {} fields, but the header has only {}",
+                    i + 1,
+                    record.len(),
+                    header.len()
+                ));
+            }
+            let mut fields = record.into_iter();
+            Ok(serde_json::Value::Object(
+                header
+                    .iter()
+                    .map(|key| {
+                        let value = fields
+                            .next()
+                            .map_or(serde_json::Value::Null, serde_json::Value::String);
+                        (key.clone(), value)
+                    })
+                    .collect(),
+            ))
+        })
+        .collect::<Result<Vec<_>>>()?;
+    Ok((values, records.next().is_some()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    mod records {
+        use super::*;
+
+        #[test]
+        fn test() {
+            assert_eq!(
+                vec![vec!["a", "b"], vec!["1", "2"]],
+                records("a,b\r\n1,2\n\n").unwrap()
+            );
+        }
+
+        #[test]
+        fn test_quoted() {
+            assert_eq!(
+                vec![vec!["x, y", "say \"hi\"", "two\nlines", ""]],
+                records("\"x, y\",\"say \"\"hi\"\"\",\"two\nlines\",\"\"").unwrap()
+            );
+        }
+
+        #[test]
+        fn test_unterminated() {
+            assert!(records("a\n\"b").is_err());
+        }
+    }
+
+    mod to_json {
+        use super::*;
+
+        #[test]
+        fn test() {
+            assert_eq!(
+                (
+                    vec![
+                        serde_json::json!({"id": "1", "name": "a"}),
+                        serde_json::json!({"id": "2", "name": null}),
+                    ],
+                    true
+                ),
+                to_json("id,name\n1,a\n2\n3,c\n", Some(2)).unwrap()
+            );
+        }
+
+        #[test]
+        fn test_too_many_fields() {
+            assert!(to_json("id\n1,2\n", None).is_err());
+        }
+    }
+}
@@ -0,0 +1,163 @@
+use std::{cell::Cell, fmt, io};
+
+use promkit::serde_json::{self, Deserializer, Map};
+use serde::de::{self, Deserialize, MapAccess, SeqAccess, Visitor};
+
+thread_local! {
+    /// The maximum depth set with [`with_max_depth`].
+    static MAX_DEPTH: Cell<usize> = const { Cell::new(usize::MAX) };
+    /// How many more levels of arrays and objects the value being parsed may nest.
+    static REMAINING: Cell<usize> = const { Cell::new(usize::MAX) };
+}
+
+/// A JSON value parsed with the nesting depth set by [`with_max_depth`],
+/// failing as soon as it nests deeper instead of after the whole value is parsed.
+pub struct Limited(pub serde_json::Value);
+
+/// Runs `f` with the nesting depth of [`Limited`] values parsed on this thread
+/// limited to `max_depth`.
+pub fn with_max_depth<T>(max_depth: usize, f: impl FnOnce() -> T) -> T {
+    let previous = (
+        MAX_DEPTH.with(|cell| cell.replace(max_depth)),
+        REMAINING.with(|cell| cell.replace(max_depth)),
+    );
+    let ret = f();
+    MAX_DEPTH.with(|cell| cell.set(previous.0));
+    REMAINING.with(|cell| cell.set(previous.1));
+    ret
+}
+
+/// Parses a single JSON value from `json_str`, nested at most `max_depth` deep.
+pub fn from_str(json_str: &str, max_depth: usize) -> serde_json::Result<serde_json::Value> {
+    with_max_depth(max_depth, || {
+        let mut deserializer = Deserializer::from_str(json_str);
+        // The depth is limited by `Limited` instead, which may allow more than serde_json's 128.
+        deserializer.disable_recursion_limit();
+        let Limited(value) = Limited::deserialize(&mut deserializer)?;
+        deserializer.end()?;
+        Ok(value)
+    })
+}
+
+/// Returns the JSON values read from `reader` one after another, each nested at most
+/// as deep as set with [`with_max_depth`], which must enclose the iteration.
+pub fn from_reader<R: io::Read>(reader: R) -> impl Iterator<Item = serde_json::Result<Limited>> {
+    let mut deserializer = Deserializer::from_reader(reader);
+    deserializer.disable_recursion_limit();
+    deserializer.into_iter::<Limited>()
+}
+
+impl<'de> Deserialize<'de> for Limited {
+    fn deserialize<D: de::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_any(LimitedVisitor).map(Limited)
+    }
+}
+
+struct LimitedVisitor;
+
+impl LimitedVisitor {
+    /// Enters an array or object, running `f` with one level less remaining.
+    fn nested<T, E: de::Error>(f: impl FnOnce() -> Result<T, E>) -> Result<T, E> {
+        let remaining = REMAINING.with(Cell::get);
+        if remaining == 0 {
+            return Err(E::custom(format!(
+                "nested deeper than the maximum depth of {} (see --max-depth)",
+                MAX_DEPTH.with(Cell::get)
+            )));
+        }
+        REMAINING.with(|cell| cell.set(remaining - 1));
+        let ret = f();
+        REMAINING.with(|cell| cell.set(remaining));
+        ret
+    }
+}
+
+impl<'de> Visitor<'de> for LimitedVisitor {
+    type Value = serde_json::Value;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("any valid JSON value")
+    }
+
+    fn visit_bool<E>(self, value: bool) -> Result<Self::Value, E> {
+        Ok(serde_json::Value::Bool(value))
+    }
+
+    fn visit_i64<E>(self, value: i64) -> Result<Self::Value, E> {
+        Ok(value.into())
+    }
+
+    fn visit_u64<E>(self, value: u64) -> Result<Self::Value, E> {
+        Ok(value.into())
+    }
+
+    fn visit_f64<E>(self, value: f64) -> Result<Self::Value, E> {
+        Ok(value.into())
+    }
+
+    fn visit_str<E>(self, value: &str) -> Result<Self::Value, E> {
+        Ok(serde_json::Value::String(value.to_string()))
+    }
+
+    fn visit_string<E>(self, value: String) -> Result<Self::Value, E> {
+        Ok(serde_json::Value::String(value))
+    }
+
+    fn visit_unit<E>(self) -> Result<Self::Value, E> {
+        Ok(serde_json::Value::Null)
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Self::Value, A::Error> {
+        Self::nested(|| {
+            let mut values = Vec::new();
+            while let Some(Limited(value)) = seq.next_element()? {
+                values.push(value);
+            }
+            Ok(serde_json::Value::Array(values))
+        })
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Self::Value, A::Error> {
+        Self::nested(|| {
+            let mut values = Map::new();
+            while let Some((key, Limited(value))) = map.next_entry::<String, Limited>()? {
+                values.insert(key, value);
+            }
+            Ok(serde_json::Value::Object(values))
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    mod from_str {
+        use super::*;
+
+        fn nested(depth: usize) -> String {
+            format!("{}1{}", "[".repeat(depth), "]".repeat(depth))
+        }
+
+        #[test]
+        fn test() {
+            assert_eq!(
+                serde_json::json!({"a": [1, -2, 3.5, "b", null, true], "c": {}}),
+                from_str(r#"{"a": [1, -2, 3.5, "b", null, true], "c": {}}"#, 2).unwrap()
+            );
+        }
+
+        #[test]
+        fn test_max_depth() {
+            assert!(from_str(&nested(2), 2).is_ok());
+            assert!(from_str(&nested(3), 2).is_err());
+            assert!(from_str("{\"a\": {\"b\": 1}}", 1).is_err());
+        }
+
+        #[test]
+        fn test_beyond_serde_json_limit() {
+            assert!(from_str(&nested(200), 200).is_ok());
+            assert!(from_str(&nested(201), 200).is_err());
+        }
+    }
+}
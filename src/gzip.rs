@@ -0,0 +1,50 @@
+use std::{io, process::Command};
+
+use anyhow::{anyhow, Result};
+
+use crate::process;
+
+/// Returns whether `bytes` starts with the magic number of gzip-compressed data.
+pub fn is_gzip(bytes: &[u8]) -> bool {
+    bytes.starts_with(&[0x1f, 0x8b])
+}
+
+/// Decompresses gzip-compressed `bytes` with the `gzip` binary on PATH,
+/// which is not installed on Windows by default.
+pub fn decompress(bytes: Vec<u8>) -> Result<Vec<u8>> {
+    let mut command = Command::new("gzip");
+    command.arg("-dc");
+    let output = process::pipe_through(&mut command, bytes).map_err(|e| match e.kind() {
+        io::ErrorKind::NotFound => anyhow!(
+            "Input is gzip-compressed, but the gzip command needed to decompress it \
+             was not found on PATH. Install gzip (e.g. with Git for Windows), \
+             or decompress the input before passing it to jnv"
+        ),
+        _ => anyhow!("Failed to run gzip: {}", e),
+    })?;
+    if !output.status.success() {
+        return Err(anyhow!(
+            "Failed to decompress gzip input: {}",
+            String::from_utf8_lossy(&output.stderr)
+                .trim()
+                .trim_start_matches("gzip: stdin: ")
+        ));
+    }
+    Ok(output.stdout)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    mod is_gzip {
+        use super::*;
+
+        #[test]
+        fn test() {
+            assert!(is_gzip(&[0x1f, 0x8b, 0x08, 0x00]));
+            assert!(!is_gzip(b"{\"a\": 1}"));
+            assert!(!is_gzip(&[0x1f]));
+        }
+    }
+}
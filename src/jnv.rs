@@ -1,62 +1,437 @@
-use std::cell::RefCell;
+use std::{
+    cell::{Cell, RefCell},
+    collections::{HashMap, HashSet, VecDeque},
+    fs, io,
+    ops::Range,
+    path::PathBuf,
+    sync::mpsc,
+    time::{Duration, Instant},
+};
 
 use anyhow::Result;
+use serde::Serialize;
 
-use jaq_interpret::{Ctx, FilterT, ParseCtx, RcIter, Val};
+use jaq_interpret::{Ctx, Filter, FilterT, ParseCtx, RcIter, Val};
+use regex::{Captures, Regex};
 
 use promkit::{
     crossterm::{
-        event::Event,
+        cursor, event,
+        event::{Event, KeyCode, KeyEvent, KeyModifiers, MouseButton, MouseEvent, MouseEventKind},
+        execute,
         style::{Attribute, Attributes, Color, ContentStyle},
+        terminal,
     },
-    json::{self, JsonNode, JsonPathSegment, JsonStream},
+    grapheme::StyledGraphemes,
+    json::{JsonNode, JsonPath, JsonPathSegment, JsonStream, JsonSyntaxKind},
     listbox,
     pane::Pane,
-    serde_json,
+    serde_json::{self, ser::PrettyFormatter},
     snapshot::Snapshot,
     style::StyleBuilder,
-    suggest::Suggest,
     switch::ActiveKeySwitcher,
-    text, text_editor, PaneFactory, Prompt, PromptSignal,
+    terminal::Terminal,
+    text, text_editor, Finalizer, PaneFactory, Prompt, PromptSignal, Renderer,
 };
 
-use crate::trie::FilterTrie;
+use crate::{browser, clipboard, jq, path, query, shell, state, trie::FilterTrie};
 
+mod diff;
+mod elapsed;
+mod find;
 mod keymap;
+pub mod palette;
+mod screenshot;
+mod search;
+mod undo;
+mod viewer;
 
-fn run_jaq(
-    query: &str,
-    json_stream: Vec<serde_json::Value>,
-) -> anyhow::Result<Vec<serde_json::Value>> {
-    let mut ret = Vec::<serde_json::Value>::new();
+/// Maximum number of recently pressed keys displayed with `--show-keys`.
+const KEY_ECHO_LENGTH: usize = 8;
+/// How long a pressed key is displayed with `--show-keys`.
+const KEY_ECHO_DURATION_MILLIS: u64 = 2000;
+/// Number of unchanged lines shown before the first change in a diff.
+const DIFF_CONTEXT_LINES: usize = 3;
+/// Maximum number of pipe stages previewed with `--pipe-preview`.
+const PIPE_PREVIEW_STAGES: usize = 3;
+/// How often new input is checked for with `--follow` while no key is pressed.
+const FOLLOW_POLL_MILLIS: u64 = 100;
 
-    for input in json_stream {
-        let mut ctx = ParseCtx::new(Vec::new());
-        ctx.insert_natives(jaq_core::core());
-        ctx.insert_defs(jaq_std::std());
+/// Runs `prompt` like [`Prompt::run`], but redraws at most `max_fps` times per second
+/// (or after every event if `max_fps` is 0).
+/// Events arriving before the next frame is due, e.g. from key repeat,
+/// are applied together and drawn once.
+pub fn run(prompt: &mut Prompt<Jnv>, max_fps: u32, mouse: bool) -> Result<String> {
+    let frame = Duration::from_secs(1)
+        .checked_div(max_fps)
+        .unwrap_or(Duration::ZERO);
 
-        let (f, errs) = jaq_parse::parse(query, jaq_parse::main());
-        if !errs.is_empty() {
-            let error_message = errs
-                .iter()
-                .map(|e| e.to_string())
-                .collect::<Vec<_>>()
-                .join(", ");
-            return Err(anyhow::anyhow!(error_message));
+    terminal::enable_raw_mode()?;
+    execute!(io::stdout(), cursor::Hide)?;
+    if mouse {
+        // Captured until the prompt is dropped.
+        execute!(io::stdout(), event::EnableMouseCapture)?;
+    }
+
+    let size = terminal::size()?;
+    let panes = prompt.renderer.create_panes(size.0, size.1);
+    let mut term = Terminal::start_session(&panes)?;
+    term.draw(&panes)?;
+    prompt.renderer.terminal_top.set(Some(term.position.1));
+    let mut last_draw = Instant::now();
+
+    // Whether the event asks to quit.
+    let handle = |prompt: &mut Prompt<Jnv>, term: &mut Terminal, ev: Event| -> Result<bool> {
+        match ev {
+            Event::Resize(_, _) => {
+                term.position = (0, 0);
+                execute!(io::stdout(), terminal::Clear(terminal::ClearType::Purge))?;
+                Ok(false)
+            }
+            Event::Mouse(event) if mouse => {
+                prompt.renderer.on_mouse(&event, term.position.1);
+                Ok(false)
+            }
+            _ => Ok(prompt.renderer.evaluate(&ev)? == PromptSignal::Quit),
+        }
+    };
+
+    'outer: loop {
+        // While waiting for a key, run the debounced filter once it is due,
+        // pick up new input with --follow and expire the keys shown with --show-keys,
+        // redrawing if anything changed.
+        while let Some(timeout) = prompt.renderer.idle_timeout() {
+            if event::poll(timeout)? {
+                break;
+            }
+            if prompt.renderer.on_idle() {
+                let size = terminal::size()?;
+                term.draw(&prompt.renderer.create_panes(size.0, size.1))?;
+                prompt.renderer.terminal_top.set(Some(term.position.1));
+                last_draw = Instant::now();
+            }
+        }
+        if handle(prompt, &mut term, event::read()?)? {
+            break;
+        }
+        while let Some(wait) = (last_draw + frame).checked_duration_since(Instant::now()) {
+            if !event::poll(wait)? {
+                break;
+            }
+            if handle(prompt, &mut term, event::read()?)? {
+                break 'outer;
+            }
         }
 
-        let f = ctx.compile(f.unwrap());
-        let inputs = RcIter::new(core::iter::empty());
-        let mut out = f.run((Ctx::new([], &inputs), Val::from(input)));
+        let size = terminal::size()?;
+        term.draw(&prompt.renderer.create_panes(size.0, size.1))?;
+        prompt.renderer.terminal_top.set(Some(term.position.1));
+        last_draw = Instant::now();
+    }
+
+    prompt.renderer.finalize()
+}
+
+/// Runs `query` against each of the `values` outside of the interface,
+/// with the variables `vars` bound like `jq --arg` and the definitions in `library`.
+pub fn run_query(
+    query: &str,
+    values: &[serde_json::Value],
+    vars: &[(String, serde_json::Value)],
+    library: &str,
+    backend: JqBackend,
+) -> Result<Vec<serde_json::Value>> {
+    run_filter(
+        query,
+        &values.iter().cloned().map(Val::from).collect::<Vec<_>>(),
+        vars,
+        library,
+        backend,
+    )
+}
+
+/// Runs `query` like [`run_query`], but passes each result to `output` as soon as
+/// it is produced instead of collecting them, stopping at the first error.
+/// Errors from `output` are returned as they are, so they can be told apart
+/// from those of the filter by downcasting to `io::Error`.
+pub fn stream_query(
+    query: &str,
+    values: &[serde_json::Value],
+    vars: &[(String, serde_json::Value)],
+    library: &str,
+    backend: JqBackend,
+    mut output: impl FnMut(serde_json::Value) -> io::Result<()>,
+) -> Result<()> {
+    if let JqBackend::System = backend {
+        let query = with_library(library, query);
+        for value in values {
+            let results = jq::run(&query, std::slice::from_ref(value), vars)
+                .map_err(|e| anyhow::anyhow!(shift_line_numbers(&e.to_string(), library)))?;
+            for result in results {
+                output(result)?;
+            }
+        }
+        return Ok(());
+    }
 
-        while let Some(Ok(val)) = out.next() {
-            ret.push(val.into());
+    let f = compile_filter(&with_library(library, query), vars, library)?;
+    for value in values {
+        let iter = RcIter::new(core::iter::empty());
+        let vals = vars.iter().map(|(_, value)| Val::from(value.clone()));
+        for val in f.run((Ctx::new(vals, &iter), Val::from(value.clone()))) {
+            output(serde_json::Value::from(
+                val.map_err(|e| anyhow::anyhow!("{}", e))?,
+            ))?;
         }
     }
+    Ok(())
+}
+
+/// Serializes `value` as JSON indented by `indent` per level.
+pub fn to_pretty_string(value: &serde_json::Value, indent: &str) -> String {
+    let mut ret = Vec::new();
+    let mut ser = serde_json::Serializer::with_formatter(
+        &mut ret,
+        PrettyFormatter::with_indent(indent.as_bytes()),
+    );
+    // Serializing a `Value` into memory cannot fail.
+    value.serialize(&mut ser).unwrap();
+    String::from_utf8(ret).unwrap_or_default()
+}
+
+/// Serializes `value` like [`to_pretty_string`], or compact if `indent` is `None`.
+/// With `raw`, a string is written as its text without quotes, like `jq -r`.
+pub fn to_output_string(value: &serde_json::Value, indent: Option<&str>, raw: bool) -> String {
+    match (value, indent) {
+        (serde_json::Value::String(s), _) if raw => s.clone(),
+        (_, Some(indent)) => to_pretty_string(value, indent),
+        (_, None) => value.to_string(),
+    }
+}
+
+/// Formats `values` line by line like the JSON viewer shows them, fully expanded.
+pub fn format_styled(values: Vec<serde_json::Value>, theme: JsonTheme) -> Vec<StyledGraphemes> {
+    viewer::State {
+        stream: JsonStream::new(values, None).into(),
+        theme,
+        find: None,
+        row_lines: Default::default(),
+        stale: false,
+    }
+    .lines()
+}
+
+/// Returns the filter to run for `filter` as typed.
+/// An empty filter shows the input data, like the identity filter.
+fn normalize_filter(filter: &str) -> &str {
+    if filter.trim().is_empty() {
+        "."
+    } else {
+        filter
+    }
+}
+
+/// Runs `query` against each of the `inputs`, with each of `vars` bound to `$name`
+/// and the definitions in `library` (e.g. `def pretty: ...;`) in scope.
+/// The query is parsed and compiled once for all inputs.
+fn run_filter(
+    query: &str,
+    inputs: &[Val],
+    vars: &[(String, serde_json::Value)],
+    library: &str,
+    backend: JqBackend,
+) -> anyhow::Result<Vec<serde_json::Value>> {
+    if let JqBackend::System = backend {
+        let inputs = inputs
+            .iter()
+            .cloned()
+            .map(serde_json::Value::from)
+            .collect::<Vec<_>>();
+        return jq::run(&with_library(library, query), &inputs, vars)
+            .map_err(|e| anyhow::anyhow!(shift_line_numbers(&e.to_string(), library)));
+    }
+    Ok(run_filter_each(query, inputs, vars, library, backend)?.concat())
+}
+
+/// Runs `query` like [`run_filter`], but keeps the results of each input separate.
+fn run_filter_each(
+    query: &str,
+    inputs: &[Val],
+    vars: &[(String, serde_json::Value)],
+    library: &str,
+    backend: JqBackend,
+) -> anyhow::Result<Vec<Vec<serde_json::Value>>> {
+    let query = &with_library(library, query);
+    if let JqBackend::System = backend {
+        let inputs = inputs
+            .iter()
+            .cloned()
+            .map(serde_json::Value::from)
+            .collect::<Vec<_>>();
+        return jq::run_each(query, &inputs, vars)
+            .map_err(|e| anyhow::anyhow!(shift_line_numbers(&e.to_string(), library)));
+    }
+
+    let f = compile_filter(query, vars, library)?;
+    inputs
+        .iter()
+        .map(|input| {
+            let iter = RcIter::new(core::iter::empty());
+            let vals = vars.iter().map(|(_, value)| Val::from(value.clone()));
+            f.run((Ctx::new(vals, &iter), input.clone()))
+                .map(|val| {
+                    val.map(serde_json::Value::from)
+                        .map_err(|e| anyhow::anyhow!("{}", e))
+                })
+                .collect()
+        })
+        .collect()
+}
+
+/// Parses and compiles `query`, built by [`with_library`] from `library`,
+/// for the embedded backend with `vars` in scope.
+fn compile_filter(
+    query: &str,
+    vars: &[(String, serde_json::Value)],
+    library: &str,
+) -> anyhow::Result<Filter> {
+    let mut ctx = ParseCtx::new(vars.iter().map(|(name, _)| name.clone()).collect());
+    ctx.insert_natives(jaq_core::core());
+    ctx.insert_defs(jaq_std::std());
+
+    let (f, errs) = jaq_parse::parse(query, jaq_parse::main());
+    if !errs.is_empty() {
+        let error_message = errs
+            .iter()
+            .map(|e| format!("{}{}", e, library_note(library, e.span().start)))
+            .collect::<Vec<_>>()
+            .join(", ");
+        return Err(anyhow::anyhow!(error_message));
+    }
+
+    let f = ctx.compile(f.unwrap());
+    // Names that are not defined are only found when compiling.
+    if !ctx.errs.is_empty() {
+        let error_message = ctx
+            .errs
+            .iter()
+            .map(|(e, span)| {
+                let name = query
+                    .chars()
+                    .skip(span.start)
+                    .take(span.len())
+                    .collect::<String>();
+                format!("{} '{}'{}", e, name, library_note(library, span.start))
+            })
+            .collect::<Vec<_>>()
+            .join(", ");
+        return Err(anyhow::anyhow!(error_message));
+    }
+    Ok(f)
+}
+
+/// Prepends the definitions in `library` to `query`, on a line of their own
+/// so that a trailing comment in the library cannot hide the query.
+fn with_library(library: &str, query: &str) -> String {
+    if library.is_empty() {
+        query.to_string()
+    } else {
+        format!("{}\n{}", library, query)
+    }
+}
+
+/// Marks an error found at the `start`-th character of a query built by
+/// [`with_library`] if it lies within the library rather than the filter.
+fn library_note(library: &str, start: usize) -> &'static str {
+    if !library.is_empty() && start < library.chars().count() {
+        " (in --library)"
+    } else {
+        ""
+    }
+}
+
+/// Rewrites the line numbers in an error from the `jq` binary so that they count
+/// from the start of the filter rather than the prepended `library`.
+fn shift_line_numbers(message: &str, library: &str) -> String {
+    if library.is_empty() {
+        return message.to_string();
+    }
+    let offset = library.matches('\n').count() + 1;
+    let re = Regex::new(r"line (\d+)").unwrap();
+    re.replace_all(message, |caps: &Captures| match caps[1].parse::<usize>() {
+        Ok(line) if line > offset => format!("line {}", line - offset),
+        Ok(line) => format!("line {} of --library", line),
+        Err(_) => caps[0].to_string(),
+    })
+    .into_owned()
+}
+
+/// Moves the cursor of `stream` onto the node at `path` within the `root`-th JSON value.
+/// Returns `false` if the node is not currently visible.
+fn move_to_path(stream: &mut viewer::Stream, root: usize, path: &[JsonPathSegment]) -> bool {
+    let Some(position) = stream.get_root(root).and_then(|node| {
+        node.flatten_visibles()
+            .iter()
+            .position(|kind| kind.path().map(Vec::as_slice) == Some(path))
+    }) else {
+        return false;
+    };
+    let offset = stream.roots()[..root]
+        .iter()
+        .map(|node| node.flatten_visibles().len())
+        .sum::<usize>();
+
+    stream.move_to_head();
+    stream.shift(0, offset + position)
+}
+
+/// Builds the stream of `values` expanded to `depth`, with every nested array
+/// and/or object collapsed and the top-level values left expanded.
+fn collapsed_stream(
+    values: Vec<serde_json::Value>,
+    depth: Option<usize>,
+    arrays: bool,
+    objects: bool,
+) -> viewer::Stream {
+    let stream = match (arrays, objects) {
+        (false, false) => JsonStream::new(values, depth),
+        // Collapsing both kinds is the same as expanding only the top level,
+        // which is set while the nodes are built.
+        (true, true) => JsonStream::new(values, Some(depth.map_or(1, |depth| depth.min(1)))),
+        _ => {
+            let mut stream = JsonStream::new(values, depth);
+            collapse_kinds(&mut stream, arrays, objects);
+            stream
+        }
+    };
+    stream.into()
+}
+
+/// Collapses every nested array and/or object in `stream` that is currently expanded,
+/// leaving the top-level values expanded.
+fn collapse_kinds(stream: &mut JsonStream, arrays: bool, objects: bool) {
+    let positions = stream
+        .flatten_kinds()
+        .iter()
+        .enumerate()
+        .filter(|(_, kind)| match kind {
+            JsonSyntaxKind::ArrayStart { path, .. } => arrays && !path.is_empty(),
+            JsonSyntaxKind::MapStart { path, .. } => objects && !path.is_empty(),
+            _ => false,
+        })
+        .map(|(i, _)| i)
+        .collect::<Vec<_>>();
 
-    Ok(ret)
+    // Collapse from the bottom so that the positions above stay valid.
+    for position in positions.into_iter().rev() {
+        stream.move_to_head();
+        stream.shift(0, position);
+        stream.toggle();
+    }
+    stream.move_to_head();
 }
 
+#[derive(Clone)]
 pub struct JsonTheme {
     /// Style for {}.
     pub curly_brackets_style: ContentStyle,
@@ -86,10 +461,339 @@ pub struct JsonTheme {
     /// the total indentation space. For example, an `indent` value of 4 means each
     /// indentation level will be 4 spaces wide.
     pub indent: usize,
+    /// Character used for indentation: `' '`, repeated `indent` times per level,
+    /// or `'\t'`, once per level. The viewer cannot draw tabs, so it shows
+    /// each one as `indent` columns; copied and written results keep the tabs.
+    pub indent_char: char,
+
+    /// Character inserted between groups of thousands in numbers, e.g. `1,000,000`.
+    /// This only affects the display; `None` renders numbers as they are.
+    pub number_separator: Option<char>,
+
+    /// Whether to draw a scrollbar on the right edge showing
+    /// where the visible lines are within the whole result.
+    pub scrollbar: bool,
+
+    /// Maximum display width of keys; longer keys are shortened with an ellipsis.
+    /// This only affects the display; copied paths use the full keys.
+    pub max_key_width: Option<usize>,
+    /// Maximum display width of strings and numbers, likewise.
+    pub max_value_width: Option<usize>,
+    /// Which part of shortened keys and values is replaced with the ellipsis.
+    pub ellipsis: Ellipsis,
+
+    /// How to number the lines in a gutter on the left, if at all.
+    pub line_numbers: Option<LineNumbers>,
+    /// Style for the line numbers.
+    pub line_number_style: ContentStyle,
+
+    /// Whether to draw tree connectors (`├─`, `└─` and `│`) instead of indentation.
+    pub tree_guides: bool,
+    /// Style for the tree connectors.
+    pub tree_guide_style: ContentStyle,
+
+    /// Whether lines wider than the pane continue aligned with the start of their value,
+    /// instead of at the left edge.
+    pub wrap_values: bool,
+
+    /// Whether top-level strings are shown without quotes, like `jq -r`.
+    pub raw_output: bool,
+
+    /// Whether scalar values are replaced by their type (`str`, `num`, `bool` or `null`)
+    /// to show only the keys and the structure. This only affects the display.
+    pub keys_only: bool,
+
+    /// Style for the text matching the search in the results.
+    pub match_style: ContentStyle,
+}
+
+impl JsonTheme {
+    /// Returns the indentation of one level in copied and written results.
+    pub fn indent_unit(&self) -> String {
+        match self.indent_char {
+            '\t' => "\t".to_string(),
+            ch => ch.to_string().repeat(self.indent),
+        }
+    }
+}
+
+/// Where the ellipsis goes when shortening keys and values.
+#[derive(Clone, Copy)]
+pub enum Ellipsis {
+    /// Keep the end, e.g. `…469f`.
+    Start,
+    /// Keep both ends, e.g. `0f8…69f`, for hashes and identifiers.
+    Middle,
+    /// Keep the start, e.g. `0f8f…`.
+    End,
+}
+
+/// How lines are numbered in the gutter of the JSON viewer.
+#[derive(Clone, Copy)]
+pub enum LineNumbers {
+    /// The line number counted from the first line.
+    Absolute,
+    /// The distance from the line under the cursor.
+    Relative,
+}
+
+/// Where the list of auto-completion suggestions is shown.
+#[derive(Clone, Copy)]
+pub enum SuggestionPlacement {
+    /// Above the filter editor.
+    Above,
+    /// Below the filter editor and hint message.
+    Below,
+}
+
+/// How multiple JSON values are joined with newlines when the result is copied.
+#[derive(Clone, Copy)]
+pub enum NewlineMode {
+    /// Newlines between values, without a trailing newline.
+    Separate,
+    /// A newline after every value, like `jq`.
+    Terminate,
+}
+
+/// Names of the sources the input values were read from, in order,
+/// each with the number of consecutive values it holds.
+pub type InputSources = Vec<(String, usize)>;
+
+/// Reads the input data again, e.g. after the input file has changed on disk.
+/// Also tells whether `--limit-length` or `--max-bytes` left values out,
+/// along with messages about what was read (e.g. the members of a tar archive).
+pub type InputLoader =
+    Box<dyn Fn() -> Result<(Vec<serde_json::Value>, InputSources, bool, Vec<String>)>>;
+
+/// Which implementation of jq runs the filters.
+#[derive(Clone, Copy)]
+pub enum JqBackend {
+    /// jaq, built into jnv.
+    Embedded,
+    /// The `jq` binary on PATH.
+    System,
+}
+
+/// Which JSON values of the input the auto-completion candidates are gathered from.
+#[derive(Clone, Copy)]
+pub enum CompletionScope {
+    /// Paths found in any of the values.
+    Union,
+    /// Only paths found in every value.
+    Intersect,
+    /// Only paths found in the first value.
+    First,
+}
+
+/// How the filter is matched against the auto-completion candidates.
+#[derive(Clone, Copy)]
+pub enum SearchMode {
+    /// Candidates starting with the filter.
+    Prefix,
+    /// Candidates containing the filter.
+    Substring,
+    /// Candidates containing the characters of the filter in order, ranked by match quality.
+    Fuzzy,
+}
+
+/// Which set of keybindings to start with.
+#[derive(Clone, Copy)]
+pub enum KeybindPreset {
+    /// The keys listed in the README, following Emacs and readline.
+    Default,
+    /// Vim-style modal editing: Esc switches to a normal mode where letters move the cursors.
+    Vi,
+}
+
+/// How the auto-completion candidates matching the filter are ordered.
+#[derive(Clone, Copy)]
+pub enum SuggestionSort {
+    /// Best matches first, as ranked by the search mode.
+    Relevance,
+    /// Alphabetically.
+    Alphabetical,
+    /// Shallower paths first.
+    Depth,
+    /// Most often picked from the suggestions first.
+    Frequency,
+}
+
+/// Returns the paths of every node in `value`, as filters.
+fn paths_of(value: &serde_json::Value) -> HashSet<String> {
+    path::paths(std::slice::from_ref(value))
+        .map(|segments| path::to_query(&segments))
+        .collect()
+}
+
+/// Returns the paths in the input data within `scope` as jq filters,
+/// without duplicates and in the order they first appear.
+pub fn completion_paths(
+    input_stream: &[serde_json::Value],
+    scope: CompletionScope,
+) -> impl Iterator<Item = String> + '_ {
+    let roots = match scope {
+        CompletionScope::First => &input_stream[..input_stream.len().min(1)],
+        CompletionScope::Union | CompletionScope::Intersect => input_stream,
+    };
+    let common = match scope {
+        CompletionScope::Intersect => roots
+            .iter()
+            .map(paths_of)
+            .reduce(|a, b| a.intersection(&b).cloned().collect()),
+        CompletionScope::Union | CompletionScope::First => None,
+    };
+
+    let mut seen = HashSet::new();
+    path::paths(roots)
+        .map(|segments| path::to_query(&segments))
+        .filter(move |query| common.as_ref().is_none_or(|common| common.contains(query)))
+        .filter(move |query| seen.insert(query.clone()))
+}
+
+/// Builds the auto-completion candidates from the paths in the input data within `scope`,
+/// stopping after `max_candidates` paths if given.
+/// Also returns whether the candidates were capped and are therefore incomplete.
+fn build_suggest(
+    input_stream: &[serde_json::Value],
+    max_candidates: Option<usize>,
+    scope: CompletionScope,
+) -> (Vec<String>, bool) {
+    let mut paths = completion_paths(input_stream, scope);
+
+    let candidates = paths
+        .by_ref()
+        .take(max_candidates.unwrap_or(usize::MAX))
+        .collect::<Vec<_>>();
+    let is_partial = paths.next().is_some();
+    (candidates, is_partial)
+}
+
+/// What the one-line input below the filter editor is for.
+#[derive(Clone, Copy)]
+enum LineInput {
+    /// A path to move the JSON viewer cursor to.
+    Goto,
+    /// A file to save the result to.
+    Save,
+    /// Text to search for in the results.
+    Find,
+}
+
+/// Settings of the interface, mostly taken from the command line.
+pub struct Options {
+    /// Depth to which the results are expanded, all of it if `None`.
+    pub json_expand_depth: Option<usize>,
+    /// Whether the hint message is hidden.
+    pub no_hint: bool,
+    /// Reads the input again on Ctrl+R, unless it came from standard input.
+    pub input_loader: Option<InputLoader>,
+    /// Line drawn between the filter editor and the JSON viewer, if any.
+    pub pane_separator: Option<text::State>,
+    /// Where the auto-completion suggestions are shown.
+    pub suggestion_placement: SuggestionPlacement,
+    /// Whether recently pressed keys are displayed at the bottom.
+    pub show_keys: bool,
+    /// Whether the status bar is displayed at the bottom.
+    pub status_bar: bool,
+    /// Whether the results after each pipe stage of the filter are previewed.
+    pub pipe_preview: bool,
+    /// Maximum number of paths gathered for auto-completion.
+    pub max_completion_candidates: Option<usize>,
+    /// The input files, empty when reading from standard input.
+    pub input_paths: Vec<PathBuf>,
+    /// Whether the input values were gathered into one array with `--slurp`.
+    pub slurp: bool,
+    /// Where each of the input values was read from.
+    pub input_sources: InputSources,
+    /// Whether `--limit-length` or `--max-bytes` left values of the input out.
+    pub input_truncated: bool,
+    /// Whether nested arrays start collapsed.
+    pub collapse_arrays: bool,
+    /// Whether nested objects start collapsed.
+    pub collapse_objects: bool,
+    /// MIME type of the data URIs copied with Alt+U.
+    pub data_uri_mime: String,
+    /// Templates the filter can be wrapped in with Alt+1 to Alt+9.
+    pub wrap_templates: Vec<String>,
+    /// Whether the filter starts locked against edits.
+    pub read_only: bool,
+    /// How multiple copied values are joined with newlines.
+    pub newline_mode: NewlineMode,
+    /// Index of the palette to start with in `palette::palettes()`.
+    pub palette: usize,
+    /// Which input values the auto-completion paths are gathered from.
+    pub completion_scope: CompletionScope,
+    /// Whether URLs under the cursor may be opened in the web browser.
+    pub allow_open_url: bool,
+    /// Whether results are copied as compact JSON rather than indented.
+    pub compact_copy: bool,
+    /// How long copying to the clipboard may take before giving up.
+    pub clipboard_timeout: Duration,
+    /// Colors from `--color` that override those of every palette.
+    pub colors: Vec<(String, Color)>,
+    /// Variables bound with `--arg` and `--argjson` for every filter.
+    pub vars: Vec<(String, serde_json::Value)>,
+    /// Definitions from `--library` prepended to every filter.
+    pub library: String,
+    /// How the filter is matched against the auto-completion candidates.
+    pub search_mode: SearchMode,
+    /// How the matching auto-completion candidates are ordered.
+    pub suggestion_sort: SuggestionSort,
+    /// Values appended to the input as they are read with `--follow`.
+    pub follow: Option<mpsc::Receiver<serde_json::Value>>,
+    /// Which implementation of jq runs the filters.
+    pub jq_backend: JqBackend,
+    /// How long to wait after the last edit to the filter before running it.
+    pub debounce: Duration,
+    /// Which set of keybindings to start with.
+    pub keybind_preset: KeybindPreset,
+    /// How long the second key of a two-key sequence may be pressed after the first.
+    pub chord_timeout: Duration,
+    /// Filters running for at least this long report how long they took.
+    pub elapsed_threshold: Duration,
+    /// Whether the pinned filters are kept in the state directory between runs.
+    pub persist_pins: bool,
+    /// Maximum number of lines of a page of the result, unsplit if `None`.
+    pub max_result_lines: Option<usize>,
+    /// The size and parse time of the input for `--stats`, shown in the status bar.
+    pub stats: Option<String>,
 }
 
 pub struct Jnv {
     input_stream: Vec<serde_json::Value>,
+    // The input data converted for jaq once, so that it is not redone per keystroke.
+    input_vals: Vec<Val>,
+    input_loader: Option<InputLoader>,
+    // How long to wait after the last edit to the filter before running it.
+    debounce: Duration,
+    // When the filter was last edited, if it has not run since.
+    pending_filter: Option<Instant>,
+    // Values appended to the input as they are read with `--follow`.
+    follow: Option<mpsc::Receiver<serde_json::Value>>,
+    // Empty when reading from standard input.
+    input_paths: Vec<PathBuf>,
+    slurp: bool,
+    input_sources: InputSources,
+    // Whether `--limit-length` or `--max-bytes` left values of the input out.
+    input_truncated: bool,
+    data_uri_mime: String,
+    allow_open_url: bool,
+    // Whether Alt+Y copies the result as compact JSON rather than indented.
+    compact_copy: bool,
+    clipboard_timeout: Duration,
+    newline_mode: NewlineMode,
+    // Index of the active palette in `palette::palettes()`.
+    palette: usize,
+    // Colors from `--color` that override those of every palette.
+    colors: Vec<(String, Color)>,
+    bracket_match_style: ContentStyle,
+    query_styles: palette::QueryStyles,
+    last_successful_query: Option<String>,
+    // Variables bound with `--arg` and `--argjson` for every filter.
+    vars: Vec<(String, serde_json::Value)>,
+    // Definitions from `--library` prepended to every filter.
+    library: String,
+    jq_backend: JqBackend,
 
     // Keybindings
     keymap: RefCell<ActiveKeySwitcher<keymap::Keymap>>,
@@ -98,91 +802,432 @@ pub struct Jnv {
     filter_editor: Snapshot<text_editor::State>,
     hint_message: Snapshot<text::State>,
     suggestions: listbox::State,
-    json: json::State,
+    json: viewer::State,
+    // The error of the last filter, shown above the result left from an earlier one.
+    filter_error: Option<String>,
+    // With --max-result-lines, all values of the result, the range of them on each page
+    // and the page shown. Empty otherwise.
+    max_result_lines: Option<usize>,
+    paged_result: Vec<serde_json::Value>,
+    result_pages: Vec<Range<usize>>,
+    result_page: usize,
 
     // Store the filter history
     trie: FilterTrie,
+    // Store the edits to the filter for undo and redo
+    edits: undo::EditHistory,
+    // The filter as of the last event, to tell which events edited it.
+    // Several events may be handled before a frame is drawn,
+    // so the snapshot taken when drawing the editor cannot tell this.
+    last_filter: String,
+    // The last two distinct results of successful filters,
+    // and the diff between them when shown.
+    results: (Option<JsonStream>, JsonStream),
+    diff_view: Option<Vec<StyledGraphemes>>,
+    // Indices of the inputs the filter returned non-null results for, and the one shown,
+    // while stepping through them.
+    matches: Option<(Vec<usize>, usize)>,
+    // Whether the session was ended with Ctrl+O to write out the result.
+    accepted: bool,
     // Store the filter suggestions
-    suggest: Suggest,
+    suggest: Vec<String>,
+    search_mode: SearchMode,
+    suggestion_sort: SuggestionSort,
+    // How many times each suggestion was picked, for `SuggestionSort::Frequency`.
+    suggestion_uses: HashMap<String, usize>,
+    max_completion_candidates: Option<usize>,
+    completion_scope: CompletionScope,
 
     json_expand_depth: Option<usize>,
+    collapse_arrays: bool,
+    collapse_objects: bool,
     no_hint: bool,
+    editor_visible: bool,
+    // How to number the lines once shown again, while they are hidden.
+    hidden_line_numbers: LineNumbers,
+    // The one-line input opened by Ctrl+G or Ctrl+S, while it is open.
+    line_input: Option<(LineInput, text_editor::State)>,
+    // The first key of a two-key sequence of the `vi` preset (e.g. `gg`) and when it was pressed.
+    pending_key: Option<(char, Instant)>,
+    // How long the second key of a sequence may be pressed after the first.
+    chord_timeout: Duration,
+    // Filters running for at least this long report how long they took.
+    elapsed_threshold: Duration,
+    // Filters pinned with Alt+K, oldest first, and the one last loaded with Alt+, or Alt+.
+    pinned_queries: Vec<String>,
+    pinned_index: Option<usize>,
+    // Whether the pinned filters are saved to the state directory.
+    persist_pins: bool,
+    // The keymap to return to when the one-line input is closed.
+    keymap_before_line_input: String,
+    // The editor's own prefix and cursor styles while it is read-only.
+    editable_styles: Option<(ContentStyle, ContentStyle)>,
+    wrap_templates: Vec<String>,
+    pane_separator: Option<text::State>,
+    suggestion_placement: SuggestionPlacement,
+
+    // Recently pressed keys with the time they were pressed,
+    // displayed at the bottom when enabled.
+    show_keys: bool,
+    recent_keys: VecDeque<(Instant, String)>,
+
+    // The row of the JSON viewer below the start of the interface, as last drawn.
+    json_pane_top: Cell<usize>,
+    // The row of the hint message below the start of the interface, as last drawn,
+    // and the terminal row the interface starts at once it has been drawn,
+    // to show how long a slow filter has been running.
+    hint_pane_top: Cell<usize>,
+    terminal_top: Cell<Option<u16>>,
+
+    // Whether to display the status bar at the bottom.
+    status_bar: bool,
+    // The size and parse time of the input for --stats, shown in the status bar.
+    stats: Option<String>,
+
+    // Summaries of the intermediate results after each pipe stage of the filter,
+    // displayed below the hint message when enabled.
+    pipe_preview: bool,
+    stage_previews: Vec<String>,
 }
 
 impl Jnv {
-    #[allow(clippy::too_many_arguments)]
     pub fn try_new(
         input_stream: Vec<serde_json::Value>,
         filter_editor: text_editor::State,
         hint_message: text::State,
         suggestions: listbox::State,
         json_theme: JsonTheme,
-        json_expand_depth: Option<usize>,
-        no_hint: bool,
+        options: Options,
     ) -> Result<Prompt<Self>> {
+        Ok(Prompt {
+            renderer: Self::new(
+                input_stream,
+                filter_editor,
+                hint_message,
+                suggestions,
+                json_theme,
+                options,
+            ),
+        })
+    }
+
+    /// Sets up the session like [`Jnv::try_new`], without the `Prompt` that drives it.
+    fn new(
+        input_stream: Vec<serde_json::Value>,
+        filter_editor: text_editor::State,
+        hint_message: text::State,
+        suggestions: listbox::State,
+        json_theme: JsonTheme,
+        options: Options,
+    ) -> Self {
+        let Options {
+            json_expand_depth,
+            no_hint,
+            input_loader,
+            pane_separator,
+            suggestion_placement,
+            show_keys,
+            status_bar,
+            pipe_preview,
+            max_completion_candidates,
+            input_paths,
+            slurp,
+            input_sources,
+            input_truncated,
+            collapse_arrays,
+            collapse_objects,
+            data_uri_mime,
+            wrap_templates,
+            read_only,
+            newline_mode,
+            palette,
+            completion_scope,
+            allow_open_url,
+            compact_copy,
+            clipboard_timeout,
+            colors,
+            vars,
+            library,
+            search_mode,
+            suggestion_sort,
+            follow,
+            jq_backend,
+            debounce,
+            keybind_preset,
+            chord_timeout,
+            elapsed_threshold,
+            persist_pins,
+            max_result_lines,
+            stats,
+        } = options;
         let mut trie = FilterTrie::default();
         trie.insert(".", input_stream.clone());
 
-        let all_kinds = JsonStream::new(input_stream.clone(), None).flatten_kinds();
-        let suggest = Suggest::from_iter(all_kinds.iter().filter_map(|kind| kind.path()).map(
-            |segments| {
-                if segments.is_empty() {
-                    ".".to_string()
-                } else {
-                    segments
-                        .iter()
-                        .enumerate()
-                        .map(|(i, segment)| match segment {
-                            JsonPathSegment::Key(key) => {
-                                if key.contains('.') || key.contains('-') || key.contains('@') {
-                                    format!(".\"{}\"", key)
-                                } else {
-                                    format!(".{}", key)
-                                }
-                            }
-                            JsonPathSegment::Index(index) => {
-                                if i == 0 {
-                                    format!(".[{}]", index)
-                                } else {
-                                    format!("[{}]", index)
-                                }
-                            }
-                        })
-                        .collect::<String>()
-                }
-            },
-        ));
+        let (suggest, is_partial) =
+            build_suggest(&input_stream, max_completion_candidates, completion_scope);
 
-        Ok(Prompt {
-            renderer: Self {
-                keymap: RefCell::new(
-                    ActiveKeySwitcher::new("default", self::keymap::default as keymap::Keymap)
-                        .register("on_suggest", self::keymap::on_suggest),
+        let result_pages = max_result_lines
+            .map(|max_lines| viewer::paginate(&input_stream, max_lines))
+            .unwrap_or_default();
+        let stream = collapsed_stream(
+            result_pages
+                .first()
+                .map(|page| input_stream[page.clone()].to_vec())
+                .unwrap_or_else(|| input_stream.clone()),
+            json_expand_depth,
+            collapse_arrays,
+            collapse_objects,
+        );
+
+        let hidden_line_numbers = json_theme.line_numbers.unwrap_or(LineNumbers::Absolute);
+
+        // The other styles of the palette are already applied to the editor and the theme.
+        let styles = palette::palettes()
+            .swap_remove(palette)
+            .with_colors(&colors);
+        let mut renderer = Self {
+            keymap: RefCell::new(
+                ActiveKeySwitcher::new(
+                    "default",
+                    match keybind_preset {
+                        KeybindPreset::Default => self::keymap::default as keymap::Keymap,
+                        KeybindPreset::Vi => self::keymap::vi_insert,
+                    },
+                )
+                .register("vi_normal", self::keymap::vi_normal)
+                .register("on_suggest", self::keymap::on_suggest)
+                .register("on_editor_hidden", self::keymap::on_editor_hidden)
+                .register("on_line_input", self::keymap::on_line_input),
+            ),
+            filter_editor: Snapshot::<text_editor::State>::new(filter_editor),
+            hint_message: Snapshot::<text::State>::new(hint_message),
+            suggestions,
+            json: viewer::State {
+                stream,
+                theme: json_theme,
+                find: None,
+                row_lines: Default::default(),
+                stale: false,
+            },
+            filter_error: None,
+            max_result_lines,
+            paged_result: if max_result_lines.is_some() {
+                input_stream.clone()
+            } else {
+                Vec::new()
+            },
+            result_pages,
+            result_page: 0,
+            trie,
+            edits: Default::default(),
+            last_filter: String::new(),
+            results: (None, JsonStream::new(input_stream.clone(), None)),
+            diff_view: None,
+            matches: None,
+            accepted: false,
+            suggest,
+            search_mode,
+            suggestion_sort,
+            // The queries saved for earlier sessions count as picked once each.
+            suggestion_uses: match suggestion_sort {
+                SuggestionSort::Frequency => state::load_last_queries().into_iter().fold(
+                    HashMap::new(),
+                    |mut uses, query| {
+                        *uses.entry(query).or_default() += 1;
+                        uses
+                    },
                 ),
-                filter_editor: Snapshot::<text_editor::State>::new(filter_editor),
-                hint_message: Snapshot::<text::State>::new(hint_message),
-                suggestions,
-                json: json::State {
-                    stream: JsonStream::new(input_stream.clone(), json_expand_depth),
-                    curly_brackets_style: json_theme.curly_brackets_style,
-                    square_brackets_style: json_theme.square_brackets_style,
-                    key_style: json_theme.key_style,
-                    string_value_style: json_theme.string_value_style,
-                    number_value_style: json_theme.number_value_style,
-                    boolean_value_style: json_theme.boolean_value_style,
-                    null_value_style: json_theme.null_value_style,
-                    active_item_attribute: json_theme.active_item_attribute,
-                    inactive_item_attribute: json_theme.inactive_item_attribute,
-                    lines: json_theme.lines,
-                    indent: json_theme.indent,
-                },
-                trie,
-                suggest,
-                json_expand_depth,
-                no_hint,
-                input_stream,
+                _ => HashMap::new(),
             },
-        })
+            max_completion_candidates,
+            completion_scope,
+            json_expand_depth,
+            collapse_arrays,
+            collapse_objects,
+            no_hint,
+            editor_visible: true,
+            hidden_line_numbers,
+            line_input: None,
+            keymap_before_line_input: String::new(),
+            pending_key: None,
+            chord_timeout,
+            elapsed_threshold,
+            pinned_queries: if persist_pins {
+                state::load_pinned_queries()
+            } else {
+                Vec::new()
+            },
+            pinned_index: None,
+            persist_pins,
+            editable_styles: None,
+            wrap_templates,
+            pane_separator,
+            suggestion_placement,
+            show_keys,
+            status_bar,
+            stats,
+            json_pane_top: Cell::new(0),
+            hint_pane_top: Cell::new(0),
+            terminal_top: Cell::new(None),
+            recent_keys: VecDeque::new(),
+            pipe_preview,
+            stage_previews: Vec::new(),
+            input_vals: input_stream.iter().cloned().map(Val::from).collect(),
+            input_stream,
+            input_loader,
+            debounce,
+            pending_filter: None,
+            follow,
+            input_paths,
+            slurp,
+            input_sources,
+            input_truncated,
+            data_uri_mime,
+            allow_open_url,
+            compact_copy,
+            clipboard_timeout,
+            bracket_match_style: styles.bracket_match_style,
+            query_styles: styles.query_styles,
+            colors,
+            vars,
+            library,
+            jq_backend,
+            last_successful_query: None,
+            newline_mode,
+            palette,
+        };
+
+        let filter = renderer
+            .filter_editor
+            .after()
+            .texteditor
+            .text_without_cursor()
+            .to_string();
+        if !filter.is_empty() {
+            renderer.apply_filter(&filter);
+        }
+        renderer.last_filter = filter;
+        if read_only {
+            renderer.toggle_read_only();
+        }
+        if is_partial {
+            renderer.warn(format!(
+                "Input has more than {} paths; completions may be incomplete",
+                max_completion_candidates.unwrap_or_default()
+            ));
+        }
+        if renderer.input_truncated {
+            renderer.warn(format!(
+                    "Showing the first {} value(s) only; the input has more (see --limit-length and --max-bytes)",
+                    renderer.input_stream.len()
+                ));
+        }
+
+        renderer
+    }
+
+    /// Builds the stream of `values` to show in the JSON viewer, expanded according
+    /// to the options, only of their first page with --max-result-lines.
+    fn new_stream(&mut self, values: Vec<serde_json::Value>) -> viewer::Stream {
+        self.new_stream_expanded_to(values, self.json_expand_depth)
+    }
+
+    /// Builds the stream like [`Jnv::new_stream`], expanded to `depth` instead.
+    fn new_stream_expanded_to(
+        &mut self,
+        values: Vec<serde_json::Value>,
+        depth: Option<usize>,
+    ) -> viewer::Stream {
+        let values = match self.max_result_lines {
+            Some(max_lines) => {
+                self.result_pages = viewer::paginate(&values, max_lines);
+                self.result_page = 0;
+                self.paged_result = values;
+                self.paged_result[self.result_pages[0].clone()].to_vec()
+            }
+            None => values,
+        };
+        collapsed_stream(values, depth, self.collapse_arrays, self.collapse_objects)
+    }
+
+    /// Returns all values of the result, not only those of the page shown.
+    fn result_values(&self) -> Vec<serde_json::Value> {
+        match self.result_pages.len() {
+            0 | 1 => self.json.values(),
+            _ => self.paged_result.clone(),
+        }
+    }
+
+    /// Shows the next (or previous) page of a result split by --max-result-lines.
+    /// Does nothing without --max-result-lines.
+    fn turn_result_page(&mut self, forward: bool) {
+        if self.max_result_lines.is_none() {
+            return;
+        }
+        let len = self.result_pages.len();
+        let page = if forward {
+            self.result_page + 1
+        } else {
+            self.result_page.wrapping_sub(1)
+        };
+        if page >= len {
+            self.warn(match len {
+                0 | 1 => "The result fits on one page".to_string(),
+                _ if forward => format!("Already on the last page ({}/{})", len, len),
+                _ => format!("Already on the first page (1/{})", len),
+            });
+            return;
+        }
+        self.result_page = page;
+        self.json.stream = collapsed_stream(
+            self.paged_result[self.result_pages[page].clone()].to_vec(),
+            self.json_expand_depth,
+            self.collapse_arrays,
+            self.collapse_objects,
+        );
+        self.diff_view = None;
+        self.notify(self.page_message());
+    }
+
+    fn page_message(&self) -> String {
+        format!(
+            "Page {}/{} of the result (PageDown/PageUp to switch)",
+            self.result_page + 1,
+            self.result_pages.len()
+        )
+    }
+
+    /// Shows `text` in the hint message as information.
+    fn notify(&mut self, text: String) {
+        self.update_hint_message(
+            text,
+            StyleBuilder::new()
+                .fgc(Color::DarkGrey)
+                .attrs(Attributes::from(Attribute::Bold))
+                .build(),
+        );
+    }
+
+    /// Shows `text` in the hint message as a warning.
+    fn warn(&mut self, text: String) {
+        self.update_hint_message(
+            text,
+            StyleBuilder::new()
+                .fgc(Color::Yellow)
+                .attrs(Attributes::from(Attribute::Bold))
+                .build(),
+        );
+    }
+
+    /// Shows `text` in the hint message as an error.
+    fn error(&mut self, text: String) {
+        self.update_hint_message(
+            text,
+            StyleBuilder::new()
+                .fgc(Color::Red)
+                .attrs(Attributes::from(Attribute::Bold))
+                .build(),
+        );
     }
 
     fn update_hint_message(&mut self, text: String, style: ContentStyle) {
@@ -192,130 +1237,2039 @@ impl Jnv {
                 .replace(text::State { text, style })
         }
     }
-}
 
-impl promkit::Finalizer for Jnv {
-    type Return = String;
+    /// Runs `filter` against the input data (or fetches the cached result)
+    /// and updates the JSON viewer and hint message accordingly.
+    fn apply_filter(&mut self, filter: &str) {
+        let filter = normalize_filter(filter);
+        self.hint_message.reset_after_to_init();
+        self.diff_view = None;
+        self.matches = None;
+        self.filter_error = None;
+        self.json.stale = false;
+        if self.pipe_preview {
+            self.stage_previews = self.preview_stages(filter);
+        }
 
-    fn finalize(&self) -> anyhow::Result<Self::Return> {
-        Ok(self
-            .filter_editor
-            .after()
-            .texteditor
-            .text_without_cursor()
-            .to_string())
+        match self.trie.exact_search(filter) {
+            Some(jsonl) => {
+                self.json.stream = self.new_stream(jsonl.clone());
+                self.record_result();
+                self.last_successful_query = Some(filter.to_string());
+                self.notify(format!(
+                    "JSON query ('{}') was already executed. Result was retrieved from cache.",
+                    filter
+                ));
+            }
+            None => {
+                let started = Instant::now();
+                let ret = self.run_filter_timed(filter);
+                let elapsed = started.elapsed();
+                match ret {
+                    Ok(ret) => {
+                        if ret.is_empty() {
+                            self.error(format!(
+                                "JSON query ('{}') was executed, but no results were returned.",
+                                filter
+                            ));
+                            if let Some(searched) = self.trie.prefix_search(filter) {
+                                self.json.stream = self.new_stream(searched.clone());
+                            }
+                        } else {
+                            let stream = self.new_stream(ret.clone());
+
+                            let is_null = stream
+                                .roots()
+                                .iter()
+                                .all(|node| node == &JsonNode::Leaf(serde_json::Value::Null));
+                            if is_null {
+                                self.warn(format!("JSON query resulted in 'null', which may indicate a typo or incorrect query: '{}'", filter));
+                                if let Some(searched) = self.trie.prefix_search(filter) {
+                                    self.json.stream = self.new_stream(searched.clone());
+                                }
+                            } else {
+                                // SUCCESS!
+                                self.trie.insert(filter, ret);
+                                self.json.stream = stream;
+                                self.record_result();
+                                self.last_successful_query = Some(filter.to_string());
+                                if self.result_pages.len() > 1 {
+                                    self.notify(self.page_message());
+                                }
+                                if elapsed >= self.elapsed_threshold {
+                                    self.notify(format!(
+                                        "JSON query took {:.1}s to execute",
+                                        elapsed.as_secs_f64()
+                                    ));
+                                }
+                            }
+                        }
+                    }
+                    Err(e) => {
+                        // Keep the message on one line, e.g. for multi-line errors of `jq`.
+                        self.filter_error =
+                            Some(e.to_string().lines().collect::<Vec<_>>().join(" "));
+                        self.json.stale = true;
+                        self.error(format!("Failed to execute jq query '{}'", filter));
+                        if let Some(searched) = self.trie.prefix_search(filter) {
+                            self.json.stream = self.new_stream(searched.clone());
+                        }
+                    }
+                }
+            }
+        }
     }
-}
 
-impl promkit::Renderer for Jnv {
-    fn create_panes(&self, width: u16, height: u16) -> Vec<Pane> {
-        vec![
-            self.filter_editor.create_pane(width, height),
-            self.hint_message.create_pane(width, height),
-            self.suggestions.create_pane(width, height),
-            self.json.create_pane(width, height),
-        ]
+    /// Runs `filter` against the input data, showing in place of the hint message
+    /// how long it has been running once that exceeds --elapsed-threshold,
+    /// so that a slow filter can be told apart from a hung one.
+    fn run_filter_timed(&self, filter: &str) -> Result<Vec<serde_json::Value>> {
+        let run = || {
+            run_filter(
+                filter,
+                &self.input_vals,
+                &self.vars,
+                &self.library,
+                self.jq_backend,
+            )
+        };
+        let Some(top) = self.terminal_top.get().filter(|_| !self.no_hint) else {
+            return run();
+        };
+        let row = top.saturating_add(self.hint_pane_top.get() as u16);
+        let threshold = self.elapsed_threshold;
+        let style = StyleBuilder::new()
+            .fgc(Color::DarkGrey)
+            .attrs(Attributes::from(Attribute::Bold))
+            .build();
+
+        let (done, receiver) = mpsc::channel();
+        std::thread::scope(|scope| {
+            scope.spawn(move || elapsed::show(row, threshold, style, receiver));
+            let ret = run();
+            let _ = done.send(());
+            ret
+        })
     }
 
-    fn evaluate(&mut self, event: &Event) -> anyhow::Result<PromptSignal> {
-        let keymap = *self.keymap.borrow_mut().get();
-        let signal = keymap(event, self);
+    /// Builds the filter editor like `text_editor::State::create_pane`, also highlighting
+    /// the bracket matching the one under the cursor, unless the filter is read-only.
+    /// The lines of a multi-line filter start on new rows, aligned after the prompt.
+    fn create_filter_editor_pane(&self, width: u16, height: u16) -> Pane {
+        // The snapshot records the filter as rendered only when it creates the pane itself,
+        // and `evaluate` compares against that to tell whether a key edited the filter.
+        self.filter_editor.create_pane(width, height);
+        let state = self.filter_editor.after();
+        let position = state.texteditor.position();
+        let raw = state.texteditor.text().to_string();
+        // Newlines are drawn as spaces, so that the cursor shows when it is on one.
+        let mut text =
+            StyledGraphemes::from(raw.replace('\n', " ")).apply_style(state.inactive_char_style);
+        if self.editable_styles.is_none() {
+            let filter = state.texteditor.text_without_cursor().to_string();
+            for (kind, range) in query::tokenize(&filter) {
+                for i in range {
+                    text = text.apply_style_at(i, self.query_styles.style(kind));
+                }
+            }
+            if let Some(matching) = query::matching_bracket(&filter, position) {
+                text = text.apply_style_at(matching, self.bracket_match_style);
+            }
+        }
+        text = text.apply_style_at(position, state.active_char_style);
+
+        let prefix = StyledGraphemes::from_str(&state.prefix, state.prefix_style);
+        let prefix_width = prefix.widths();
+        let newlines = raw
+            .chars()
+            .enumerate()
+            .filter(|(_, ch)| *ch == '\n')
+            .map(|(i, _)| i)
+            .collect::<Vec<_>>();
+        let mut lines = vec![prefix];
+        // The line of the cursor and its column in it.
+        let mut cursor = (0, prefix_width);
+        for (i, g) in text.iter().enumerate() {
+            let last = lines.len() - 1;
+            if i == position {
+                cursor = (last, lines[last].widths());
+            }
+            lines[last].push_back(g.clone());
+            if newlines.contains(&i) {
+                lines.push(StyledGraphemes::from(" ".repeat(prefix_width)));
+            }
+        }
+
+        let width = (width as usize).max(1);
+        let mut rows = vec![];
+        let mut cursor_row = 0;
+        for (i, line) in lines.iter().enumerate() {
+            if i == cursor.0 {
+                cursor_row = rows.len() + cursor.1 / width;
+            }
+            rows.extend(line.matrixify(width, usize::MAX, 0).0);
+        }
+        // Keep the row of the cursor in view when the filter has more rows than fit.
+        let height = state
+            .lines
+            .map_or(height as usize, |lines| lines.min(height as usize))
+            .max(1);
+        let start = cursor_row
+            .saturating_sub(height - 1)
+            .min(rows.len().saturating_sub(height));
+        Pane::new(rows.into_iter().skip(start).take(height).collect(), 0)
+    }
+
+    /// Builds the `--status-bar` line: the number of input values,
+    /// the path of the node under the cursor and whether the current filter succeeded.
+    fn create_status_bar_pane(&self, width: u16) -> Pane {
         let filter = self
             .filter_editor
             .after()
             .texteditor
             .text_without_cursor()
             .to_string();
-
-        // Check if the query has changed
-        if filter
-            != self
-                .filter_editor
-                .borrow_before()
-                .texteditor
-                .text_without_cursor()
-                .to_string()
+        let (status, color) = if self.pending_filter.is_some() {
+            ("…", Color::DarkGrey)
+        } else if filter.is_empty()
+            || self.last_successful_query.as_deref() == Some(normalize_filter(&filter))
         {
-            self.hint_message.reset_after_to_init();
-
-            match self.trie.exact_search(&filter) {
-                Some(jsonl) => {
-                    self.json.stream = JsonStream::new(jsonl.clone(), self.json_expand_depth);
-                    self.update_hint_message(
-                        format!(
-                            "JSON query ('{}') was already executed. Result was retrieved from cache.",
-                            &filter
-                        ),
-                        StyleBuilder::new()
-                            .fgc(Color::DarkGrey)
-                            .attrs(Attributes::from(Attribute::Bold))
-                            .build(),
-                    );
+            ("OK", Color::Green)
+        } else {
+            ("ERR", Color::Red)
+        };
+        let (_, path) = self.json.stream.current_root_and_path_from_root();
+        let path = path.map(|path| path::to_query(&path)).unwrap_or_default();
+
+        let style = StyleBuilder::new()
+            .attrs(Attributes::from(Attribute::Reverse))
+            .build();
+        let page = match self.result_pages.len() {
+            0 | 1 => String::new(),
+            len => format!(
+                "page {}/{}{} │ ",
+                self.result_page + 1,
+                len,
+                if self.result_page + 1 < len {
+                    ", more"
+                } else {
+                    ""
                 }
-                None => {
-                    match run_jaq(&filter, self.input_stream.clone()) {
-                        Ok(ret) => {
-                            if ret.is_empty() {
-                                self.update_hint_message(
-                                    format!(
-                                        "JSON query ('{}') was executed, but no results were returned.",
-                                        &filter
-                                    ),
-                                    StyleBuilder::new()
-                                        .fgc(Color::Red)
-                                        .attrs(Attributes::from(Attribute::Bold))
-                                        .build(),
-                                );
-                                if let Some(searched) = self.trie.prefix_search(&filter) {
-                                    self.json.stream =
-                                        JsonStream::new(searched.clone(), self.json_expand_depth);
-                                }
-                            } else {
-                                let stream = JsonStream::new(ret.clone(), self.json_expand_depth);
-
-                                let is_null = stream
-                                    .roots()
-                                    .iter()
-                                    .all(|node| node == &JsonNode::Leaf(serde_json::Value::Null));
-                                if is_null {
-                                    self.update_hint_message(
-                                        format!("JSON query resulted in 'null', which may indicate a typo or incorrect query: '{}'", &filter),
-                                        StyleBuilder::new()
-                                            .fgc(Color::Yellow)
-                                            .attrs(Attributes::from(Attribute::Bold))
-                                            .build(),
-                                    );
-                                    if let Some(searched) = self.trie.prefix_search(&filter) {
-                                        self.json.stream = JsonStream::new(
-                                            searched.clone(),
-                                            self.json_expand_depth,
-                                        );
-                                    }
-                                } else {
-                                    // SUCCESS!
-                                    self.trie.insert(&filter, ret);
-                                    self.json.stream = stream;
-                                }
-                            }
-                        }
-                        Err(_) => {
-                            self.update_hint_message(
-                                format!("Failed to execute jq query '{}'", &filter),
-                                StyleBuilder::new()
-                                    .fgc(Color::Red)
-                                    .attrs(Attributes::from(Attribute::Bold))
-                                    .build(),
-                            );
-                            if let Some(searched) = self.trie.prefix_search(&filter) {
-                                self.json.stream =
-                                    JsonStream::new(searched.clone(), self.json_expand_depth);
-                            }
-                            return signal;
+            ),
+        };
+        let stats = match &self.stats {
+            Some(stats) => format!("{} │ ", stats),
+            None => String::new(),
+        };
+        let text = format!(
+            " {} value(s){} │ {} │ {}{}",
+            self.input_stream.len(),
+            if self.input_truncated {
+                " (truncated)"
+            } else {
+                ""
+            },
+            path,
+            page,
+            stats
+        );
+        let padding = (width as usize).saturating_sub(
+            StyledGraphemes::from(&text).widths() + StyledGraphemes::from(status).widths() + 1,
+        );
+        Pane::new(
+            StyledGraphemes::from_iter([
+                StyledGraphemes::from_str(text, style),
+                StyledGraphemes::from_str(
+                    status,
+                    StyleBuilder::new()
+                        .fgc(color)
+                        .attrs(Attributes::from(Attribute::Reverse) | Attribute::Bold)
+                        .build(),
+                ),
+                StyledGraphemes::from_str(" ".repeat(padding + 1), style),
+            ])
+            .matrixify(width as usize, 1, 0)
+            .0,
+            0,
+        )
+    }
+
+    /// Summarizes the result after each top-level pipe stage of `filter` except the last,
+    /// which is shown in the JSON viewer, keeping only the last few stages.
+    fn preview_stages(&self, filter: &str) -> Vec<String> {
+        let stages = query::split_pipes(filter);
+        (1..stages.len())
+            .skip((stages.len() - 1).saturating_sub(PIPE_PREVIEW_STAGES))
+            .map(|n| {
+                let prefix = stages[..n].join("|");
+                let summary = match run_filter(
+                    &prefix,
+                    &self.input_vals,
+                    &self.vars,
+                    &self.library,
+                    self.jq_backend,
+                ) {
+                    Ok(ret) => match ret.first() {
+                        Some(first) if ret.len() > 1 => {
+                            format!("{} (+{} more)", first, ret.len() - 1)
                         }
-                    }
-                }
-            }
+                        Some(first) => first.to_string(),
+                        None => "(no results)".to_string(),
+                    },
+                    Err(_) => "(error)".to_string(),
+                };
+                format!("{} => {}", prefix.trim(), summary)
+            })
+            .collect()
+    }
+
+    /// Renders the interface as it looks in a terminal of the given size, as plain text.
+    pub fn screenshot(&self, width: u16, height: u16) -> String {
+        screenshot::compose(&self.create_panes(width, height), height as usize)
+            .iter()
+            .map(|row| format!("{}\n", row.trim_end()))
+            .collect()
+    }
+
+    /// Copies the screen as it currently looks to the clipboard as plain text.
+    fn copy_screenshot(&mut self) {
+        let (width, height) = terminal::size().unwrap_or((80, 24));
+        match clipboard::copy(&self.screenshot(width, height), self.clipboard_timeout) {
+            Ok(()) => self.notify("Copied screen to clipboard".to_string()),
+            Err(e) => self.error(format!("Failed to copy to clipboard: {}", e)),
+        }
+    }
+
+    /// Switches to the next built-in palette, wrapping around to the first.
+    fn cycle_palette(&mut self) {
+        let mut palettes = palette::palettes();
+        self.palette = (self.palette + 1) % palettes.len();
+        let palette = palettes.swap_remove(self.palette).with_colors(&self.colors);
+
+        palette.apply(self.filter_editor.after_mut(), &mut self.json.theme);
+        self.bracket_match_style = palette.bracket_match_style;
+        self.query_styles = palette.query_styles;
+        // Keep the read-only look, and restore this palette when it is unlocked.
+        if let Some(styles) = &mut self.editable_styles {
+            let filter_editor = self.filter_editor.after_mut();
+            *styles = (filter_editor.prefix_style, filter_editor.active_char_style);
+            filter_editor.prefix_style = StyleBuilder::new().fgc(Color::DarkGrey).build();
+            filter_editor.active_char_style = filter_editor.inactive_char_style;
+        }
+
+        self.notify(format!("Switched to the '{}' palette", palette.name));
+    }
+
+    /// Opens the string value under the cursor in the web browser if it is a URL.
+    fn open_url(&mut self) {
+        if !self.allow_open_url {
+            self.warn("Opening URLs is disabled (enable it with --allow-open-url)".to_string());
+            return;
+        }
+
+        let (root, path) = self.json.stream.current_root_and_path_from_root();
+        let url = match path.as_ref().and_then(|path| root.get(path)) {
+            Some(JsonNode::Leaf(serde_json::Value::String(s))) if browser::is_url(s) => s.clone(),
+            _ => {
+                self.warn("The value under the cursor is not a URL".to_string());
+                return;
+            }
+        };
+        match browser::open(&url) {
+            Ok(()) => self.notify(format!("Opened {}", url)),
+            Err(e) => self.error(format!("Failed to open {}: {}", url, e)),
+        }
+    }
+
+    /// Remembers the result in the JSON viewer as the latest successful one.
+    fn record_result(&mut self) {
+        if self.results.1.roots() != self.json.stream.roots() {
+            let previous =
+                std::mem::replace(&mut self.results.1, JsonStream::clone(&self.json.stream));
+            self.results.0 = Some(previous);
+        }
+    }
+
+    /// Shows only the results of the next (or previous) input the filter returned
+    /// non-null results for,
+    /// wrapping around at either end.
+    fn cycle_matches(&mut self, forward: bool) {
+        let filter = self
+            .filter_editor
+            .after()
+            .texteditor
+            .text_without_cursor()
+            .to_string();
+        let filter = normalize_filter(&filter);
+
+        let (indices, current) = match self.matches.take() {
+            Some((indices, current)) => {
+                let len = indices.len();
+                let next = if forward {
+                    (current + 1) % len
+                } else {
+                    (current + len - 1) % len
+                };
+                (indices, next)
+            }
+            None => {
+                let Ok(results) = run_filter_each(
+                    filter,
+                    &self.input_vals,
+                    &self.vars,
+                    &self.library,
+                    self.jq_backend,
+                ) else {
+                    self.error(format!("Failed to execute jq query '{}'", filter));
+                    return;
+                };
+                let indices = results
+                    .iter()
+                    .enumerate()
+                    .filter(|(_, ret)| ret.iter().any(|val| !val.is_null()))
+                    .map(|(i, _)| i)
+                    .collect::<Vec<_>>();
+                if indices.is_empty() {
+                    self.warn(format!(
+                        "JSON query ('{}') returned no results for any input",
+                        filter
+                    ));
+                    return;
+                }
+                let current = if forward { 0 } else { indices.len() - 1 };
+                (indices, current)
+            }
+        };
+
+        let input = indices[current];
+        let ret = run_filter(
+            filter,
+            &self.input_vals[input..=input],
+            &self.vars,
+            &self.library,
+            self.jq_backend,
+        )
+        .unwrap_or_default();
+        self.json.stream = self.new_stream(ret);
+        self.diff_view = None;
+        let source = match self.input_source(input) {
+            Some(name) => format!(" from {}", name),
+            None => String::new(),
+        };
+        self.notify(format!(
+            "Match {} of {} (input {} of {}{})",
+            current + 1,
+            indices.len(),
+            input + 1,
+            self.input_vals.len(),
+            source
+        ));
+        self.matches = Some((indices, current));
+    }
+
+    /// Returns the name of the source the input value at `index` was read from,
+    /// if there is more than one.
+    fn input_source(&self, index: usize) -> Option<&str> {
+        if self.input_sources.len() < 2 {
+            return None;
+        }
+        let mut end = 0;
+        self.input_sources.iter().find_map(|(name, len)| {
+            end += len;
+            (index < end).then_some(name.as_str())
+        })
+    }
+
+    /// Shows the lines added and removed between the results of the last two
+    /// successful filters in place of the JSON viewer, or hides them again.
+    fn toggle_diff(&mut self) {
+        if self.diff_view.take().is_some() {
+            return;
+        }
+        let Some(previous) = &self.results.0 else {
+            self.warn("No previous result to compare with".to_string());
+            return;
+        };
+
+        let pretty = |stream: &JsonStream| {
+            stream
+                .roots()
+                .iter()
+                .map(|node| {
+                    serde_json::to_string_pretty(&viewer::to_value(node)).unwrap_or_default()
+                })
+                .collect::<Vec<_>>()
+                .join("\n")
+        };
+        let (old, new) = (pretty(previous), pretty(&self.results.1));
+        let old = old.lines().collect::<Vec<_>>();
+        let new = new.lines().collect::<Vec<_>>();
+        let lines = diff::diff(&old, &new);
+
+        // Start a few lines before the first change.
+        let first_change = lines
+            .iter()
+            .position(|line| !matches!(line, diff::Line::Same(_)))
+            .unwrap_or(0);
+        self.diff_view = Some(
+            lines
+                .iter()
+                .skip(first_change.saturating_sub(DIFF_CONTEXT_LINES))
+                .map(|line| match line {
+                    diff::Line::Same(line) => StyledGraphemes::from_str(
+                        format!("  {}", line),
+                        StyleBuilder::new()
+                            .attrs(Attributes::from(Attribute::Dim))
+                            .build(),
+                    ),
+                    diff::Line::Removed(line) => StyledGraphemes::from_str(
+                        format!("- {}", line),
+                        StyleBuilder::new().fgc(Color::Red).build(),
+                    ),
+                    diff::Line::Added(line) => StyledGraphemes::from_str(
+                        format!("+ {}", line),
+                        StyleBuilder::new().fgc(Color::Green).build(),
+                    ),
+                })
+                .collect(),
+        );
+    }
+
+    /// Switches the line numbers between absolute and relative.
+    fn toggle_relative_line_numbers(&mut self) {
+        let (mode, message) = match self.json.theme.line_numbers {
+            Some(LineNumbers::Absolute) => (LineNumbers::Relative, "Relative line numbers"),
+            Some(LineNumbers::Relative) => (LineNumbers::Absolute, "Absolute line numbers"),
+            None => {
+                self.warn("Line numbers are not shown (show them with Alt+L)".to_string());
+                return;
+            }
+        };
+        self.json.theme.line_numbers = Some(mode);
+        self.notify(message.to_string());
+    }
+
+    /// Shows or hides the line numbers, keeping whether they are relative.
+    fn toggle_line_numbers(&mut self) {
+        let message = match self.json.theme.line_numbers.take() {
+            Some(mode) => {
+                self.hidden_line_numbers = mode;
+                "Line numbers hidden"
+            }
+            None => {
+                self.json.theme.line_numbers = Some(self.hidden_line_numbers);
+                "Line numbers shown"
+            }
+        };
+        self.notify(message.to_string());
+    }
+
+    /// Switches between showing top-level strings raw, like `jq -r`, and as JSON.
+    fn toggle_raw_output(&mut self) {
+        self.json.theme.raw_output = !self.json.theme.raw_output;
+        self.notify(
+            if self.json.theme.raw_output {
+                "Raw output: strings are shown without quotes"
+            } else {
+                "JSON output: strings are shown quoted"
+            }
+            .to_string(),
+        );
+    }
+
+    /// Switches between wrapping long lines aligned with their value and at the left edge.
+    fn toggle_wrap_values(&mut self) {
+        self.json.theme.wrap_values = !self.json.theme.wrap_values;
+        self.notify(
+            if self.json.theme.wrap_values {
+                "Wrapping long values aligned with their start"
+            } else {
+                "Wrapping long values at the left edge"
+            }
+            .to_string(),
+        );
+    }
+
+    /// Switches the JSON viewer between indentation and tree connectors.
+    fn toggle_tree_guides(&mut self) {
+        self.json.theme.tree_guides = !self.json.theme.tree_guides;
+        self.notify(
+            if self.json.theme.tree_guides {
+                "Showing the nesting with tree connectors"
+            } else {
+                "Showing the nesting with indentation"
+            }
+            .to_string(),
+        );
+    }
+
+    /// Switches the JSON viewer between showing the values and only their types.
+    fn toggle_keys_only(&mut self) {
+        self.json.theme.keys_only = !self.json.theme.keys_only;
+        self.notify(
+            if self.json.theme.keys_only {
+                "Showing the keys with the types of the values"
+            } else {
+                "Showing the keys with the values"
+            }
+            .to_string(),
+        );
+    }
+
+    /// Replaces the filter with `filter`, empty to show the input data as it is.
+    /// The filter is run again even if it is unchanged, e.g. to leave the view of Alt+C.
+    fn reset_filter(&mut self, filter: &str) {
+        let texteditor = &mut self.filter_editor.after_mut().texteditor;
+        if texteditor.text_without_cursor().to_string() == filter {
+            self.apply_filter(filter);
+        } else {
+            texteditor.replace(filter);
+        }
+    }
+
+    /// Reverts the last edit to the filter.
+    fn undo(&mut self) {
+        let texteditor = &mut self.filter_editor.after_mut().texteditor;
+        if let Some(filter) = self
+            .edits
+            .undo(&texteditor.text_without_cursor().to_string())
+        {
+            texteditor.replace(&filter);
+        }
+    }
+
+    /// Reapplies the last edit to the filter reverted by undo.
+    fn redo(&mut self) {
+        let texteditor = &mut self.filter_editor.after_mut().texteditor;
+        if let Some(filter) = self
+            .edits
+            .redo(&texteditor.text_without_cursor().to_string())
+        {
+            texteditor.replace(&filter);
+        }
+    }
+
+    /// Returns whether the filter editor is read-only.
+    fn read_only(&self) -> bool {
+        self.editable_styles.is_some()
+    }
+
+    /// Switches the filter editor between read-only and editable.
+    /// While read-only, the prompt is greyed out and the cursor is hidden.
+    fn toggle_read_only(&mut self) {
+        let filter_editor = self.filter_editor.after_mut();
+        let message = match self.editable_styles.take() {
+            Some((prefix_style, active_char_style)) => {
+                filter_editor.prefix_style = prefix_style;
+                filter_editor.active_char_style = active_char_style;
+                "Filter is editable"
+            }
+            None => {
+                self.editable_styles =
+                    Some((filter_editor.prefix_style, filter_editor.active_char_style));
+                filter_editor.prefix_style = StyleBuilder::new().fgc(Color::DarkGrey).build();
+                filter_editor.active_char_style = filter_editor.inactive_char_style;
+                "Filter is read-only (press Alt+R to edit)"
+            }
+        };
+        self.notify(message.to_string());
+    }
+
+    /// Wraps the filter in the `index`-th wrap template,
+    /// replacing the template's `{}` placeholder with the filter.
+    fn wrap_filter(&mut self, index: usize) {
+        let Some(template) = self.wrap_templates.get(index) else {
+            return;
+        };
+        let texteditor = &mut self.filter_editor.after_mut().texteditor;
+        let filter = texteditor.text_without_cursor().to_string();
+        texteditor.replace(&template.replacen("{}", filter.trim(), 1));
+    }
+
+    /// Inserts the jq path of the node under the cursor in the JSON viewer
+    /// into the filter at the cursor, piping into it after an existing filter.
+    fn insert_path(&mut self) {
+        let (_, path) = self.json.stream.current_root_and_path_from_root();
+        let Some(path) = path else {
+            return;
+        };
+        let path = path::to_query(&path);
+
+        let texteditor = &mut self.filter_editor.after_mut().texteditor;
+        let filter = texteditor.text_without_cursor().to_string();
+        if filter.trim().is_empty() || filter.trim() == "." {
+            texteditor.replace(&path);
+            return;
+        }
+        let before_cursor = filter
+            .chars()
+            .take(texteditor.position())
+            .collect::<String>();
+        texteditor.insert_chars(
+            &query::path_insertion(&before_cursor, &path)
+                .chars()
+                .collect(),
+        );
+    }
+
+    /// Copies the path of the node under the cursor to the clipboard, as a filter.
+    fn copy_path(&mut self) {
+        let (_, path) = self.json.stream.current_root_and_path_from_root();
+        let Some(path) = path else {
+            return;
+        };
+        let path = path::to_query(&path);
+        match clipboard::copy(&path, self.clipboard_timeout) {
+            Ok(()) => self.notify(format!("Copied to clipboard: {}", path)),
+            Err(e) => self.error(format!("Failed to copy to clipboard: {}", e)),
+        }
+    }
+
+    /// Builds a `jq` command line that reproduces the current view from the shell.
+    fn jq_command(&self) -> String {
+        let filter = self
+            .filter_editor
+            .after()
+            .texteditor
+            .text_without_cursor()
+            .to_string();
+
+        let mut command = vec!["jq".to_string()];
+        if self.slurp {
+            command.push("-s".to_string());
+        }
+        if self.json.theme.raw_output {
+            command.push("-r".to_string());
+        }
+        if self.json.theme.indent_char == '\t' {
+            command.push("--tab".to_string());
+        } else if self.json.theme.indent != 2 {
+            command.push("--indent".to_string());
+            command.push(self.json.theme.indent.to_string());
+        }
+        for (name, value) in &self.vars {
+            match value {
+                serde_json::Value::String(s) => {
+                    command.extend(["--arg".to_string(), shell::quote(name), shell::quote(s)])
+                }
+                value => command.extend([
+                    "--argjson".to_string(),
+                    shell::quote(name),
+                    shell::quote(&value.to_string()),
+                ]),
+            }
+        }
+        command.push(shell::quote(&with_library(
+            &self.library,
+            if filter.is_empty() { "." } else { &filter },
+        )));
+        for path in &self.input_paths {
+            command.push(shell::quote(&path.to_string_lossy()));
+        }
+        command.join(" ")
+    }
+
+    /// Copies the `jq` command reproducing the current view to the clipboard.
+    fn copy_jq_command(&mut self) {
+        let command = self.jq_command();
+        match clipboard::copy(&command, self.clipboard_timeout) {
+            Ok(()) => self.notify(format!("Copied to clipboard: {}", command)),
+            Err(e) => self.error(format!("Failed to copy to clipboard: {}", e)),
+        }
+    }
+
+    /// Copies the result shown in the JSON viewer to the clipboard,
+    /// indented like the viewer or compact according to `compact_copy`.
+    fn copy_result(&mut self) {
+        let text = if self.compact_copy {
+            self.result_text()
+        } else {
+            let values = self
+                .result_values()
+                .iter()
+                .map(|value| {
+                    to_output_string(
+                        value,
+                        Some(&self.json.theme.indent_unit()),
+                        self.json.theme.raw_output,
+                    )
+                })
+                .collect::<Vec<_>>();
+            match self.newline_mode {
+                NewlineMode::Separate => values.join("\n"),
+                NewlineMode::Terminate => {
+                    values.iter().map(|value| format!("{}\n", value)).collect()
+                }
+            }
+        };
+        match clipboard::copy(&text, self.clipboard_timeout) {
+            Ok(()) => self.notify(format!(
+                "Copied result to clipboard ({})",
+                if self.compact_copy {
+                    "compact"
+                } else {
+                    "indented"
+                }
+            )),
+            Err(e) => self.error(format!("Failed to copy to clipboard: {}", e)),
+        }
+    }
+
+    /// Pins the filter so that Alt+, and Alt+. can load it back, or unpins it if already pinned.
+    fn toggle_pin(&mut self) {
+        let filter = self
+            .filter_editor
+            .after()
+            .texteditor
+            .text_without_cursor()
+            .to_string();
+        if filter.trim().is_empty() {
+            return;
+        }
+        let message = match self
+            .pinned_queries
+            .iter()
+            .position(|pinned| pinned == &filter)
+        {
+            Some(index) => {
+                self.pinned_queries.remove(index);
+                self.pinned_index = None;
+                format!("Unpinned the filter ({} pinned)", self.pinned_queries.len())
+            }
+            None => {
+                self.pinned_queries.push(filter);
+                self.pinned_index = Some(self.pinned_queries.len() - 1);
+                format!(
+                    "Pinned the filter ({} pinned, press Alt+, or Alt+. to switch)",
+                    self.pinned_queries.len()
+                )
+            }
+        };
+        if self.persist_pins {
+            if let Err(e) = state::save_pinned_queries(&self.pinned_queries) {
+                self.error(format!("Failed to save the pinned filters: {}", e));
+                return;
+            }
+        }
+        self.notify(message);
+    }
+
+    /// Replaces the filter with the next pinned one, or the previous one unless `forward`,
+    /// wrapping around at the ends.
+    fn cycle_pinned(&mut self, forward: bool) {
+        let len = self.pinned_queries.len();
+        if len == 0 {
+            self.warn("No pinned filters (press Alt+K to pin the filter)".to_string());
+            return;
+        }
+        let index = match self.pinned_index {
+            Some(index) if forward => (index + 1) % len,
+            Some(index) => (index + len - 1) % len,
+            None if forward => 0,
+            None => len - 1,
+        };
+        self.pinned_index = Some(index);
+        self.filter_editor
+            .after_mut()
+            .texteditor
+            .replace(&self.pinned_queries[index]);
+        self.notify(format!("Pinned filter {}/{}", index + 1, len));
+    }
+
+    /// Switches Alt+Y between copying compact and indented JSON.
+    fn toggle_compact_copy(&mut self) {
+        self.compact_copy = !self.compact_copy;
+        self.notify(format!(
+            "Alt+Y copies {} JSON",
+            if self.compact_copy {
+                "compact"
+            } else {
+                "indented"
+            }
+        ));
+    }
+
+    /// Copies `{"query": ..., "result": ...}` to the clipboard, compact or indented
+    /// like Alt+Y. A result of several values is copied as an array of them.
+    fn copy_query_and_result(&mut self) {
+        let mut values = self.result_values();
+        let result = match values.len() {
+            1 => values.remove(0),
+            _ => serde_json::Value::Array(values),
+        };
+        let payload = serde_json::json!({
+            "query": self
+                .filter_editor
+                .after()
+                .texteditor
+                .text_without_cursor()
+                .to_string(),
+            "result": result,
+        });
+        let indent = (!self.compact_copy).then(|| self.json.theme.indent_unit());
+        match clipboard::copy(
+            &to_output_string(&payload, indent.as_deref(), false),
+            self.clipboard_timeout,
+        ) {
+            Ok(()) => self.notify("Copied query and result to clipboard".to_string()),
+            Err(e) => self.error(format!("Failed to copy to clipboard: {}", e)),
+        }
+    }
+
+    /// Serializes the result shown in the JSON viewer with one JSON value per line,
+    /// terminated by newlines according to `newline_mode`.
+    fn result_text(&self) -> String {
+        let lines = self
+            .result_values()
+            .iter()
+            .map(|value| to_output_string(value, None, self.json.theme.raw_output))
+            .collect::<Vec<_>>();
+        match self.newline_mode {
+            NewlineMode::Separate => lines.join("\n"),
+            NewlineMode::Terminate => lines.iter().map(|line| format!("{}\n", line)).collect(),
+        }
+    }
+
+    /// Ends the session, writing out the result shown in the JSON viewer.
+    fn accept(&mut self) -> PromptSignal {
+        self.accepted = true;
+        PromptSignal::Quit
+    }
+
+    /// Returns the last filter that ran without errors and returned non-null results.
+    pub fn last_successful_query(&self) -> Option<&str> {
+        self.last_successful_query.as_deref()
+    }
+
+    /// Returns the result to write out if the session was ended with Ctrl+O.
+    pub fn accepted_result(&self) -> Option<String> {
+        self.accepted.then(|| self.result_text())
+    }
+
+    /// Copies the result shown in the JSON viewer to the clipboard as a data URI.
+    fn copy_data_uri(&mut self) {
+        let data = self.result_text();
+        match clipboard::copy(
+            &clipboard::data_uri(&self.data_uri_mime, &data),
+            self.clipboard_timeout,
+        ) {
+            Ok(()) => self.notify(format!(
+                "Copied result to clipboard as {} data URI",
+                self.data_uri_mime
+            )),
+            Err(e) => self.error(format!("Failed to copy to clipboard: {}", e)),
+        }
+    }
+
+    /// Reads the input data again and re-runs the current filter against it,
+    /// keeping the cursor of the JSON viewer on the same line where possible.
+    fn reload_input(&mut self) {
+        let Some(input_loader) = &self.input_loader else {
+            self.warn("Input from standard input cannot be reloaded".to_string());
+            return;
+        };
+
+        match input_loader() {
+            Ok((input_stream, input_sources, input_truncated, notes)) => {
+                self.replace_input(input_stream);
+                self.input_sources = input_sources;
+                self.input_truncated = input_truncated;
+                let message = match notes.as_slice() {
+                    [] => "Input was reloaded".to_string(),
+                    notes => format!("Input was reloaded. {}", notes.join(". ")),
+                };
+                if input_truncated {
+                    self.warn(message);
+                } else {
+                    self.notify(message);
+                }
+            }
+            Err(e) => {
+                self.error(format!("Failed to reload input: {}", e));
+            }
+        }
+    }
+
+    /// Replaces the input data, re-running the current filter
+    /// and keeping the cursor on the same line.
+    fn replace_input(&mut self, input_stream: Vec<serde_json::Value>) {
+        let position = self.json.stream.position();
+
+        self.trie = FilterTrie::default();
+        self.trie.insert(".", input_stream.clone());
+        (self.suggest, _) = build_suggest(
+            &input_stream,
+            self.max_completion_candidates,
+            self.completion_scope,
+        );
+        self.json.stream = self.new_stream(input_stream.clone());
+        self.input_vals = input_stream.iter().cloned().map(Val::from).collect();
+        self.input_stream = input_stream;
+
+        let filter = self
+            .filter_editor
+            .after()
+            .texteditor
+            .text_without_cursor()
+            .to_string();
+        if !filter.is_empty() {
+            self.apply_filter(&filter);
+        }
+        self.json.stream.shift(0, position);
+    }
+
+    /// Appends `values` to the input, running the current filter against them only
+    /// and adding their results after those shown, whose nodes stay folded or
+    /// unfolded as they are.
+    /// Falls back to re-running the filter against the whole input if the results
+    /// shown are not simply those of the current filter (e.g. it failed, is waiting
+    /// for `--debounce`, or the results are split into pages).
+    fn append_input(&mut self, values: Vec<serde_json::Value>) {
+        let filter = self
+            .filter_editor
+            .after()
+            .texteditor
+            .text_without_cursor()
+            .to_string();
+        let filter = if filter.trim().is_empty() {
+            "."
+        } else {
+            &filter
+        };
+        let shown = self
+            .trie
+            .exact_search(filter)
+            .filter(|_| {
+                // The input is shown until a filter runs.
+                self.last_successful_query.as_deref().unwrap_or(".") == filter
+                    && !self.json.stale
+                    && self.pending_filter.is_none()
+                    && self.diff_view.is_none()
+                    && !self.pipe_preview
+                    && self.max_result_lines.is_none()
+            })
+            .cloned();
+
+        let vals = values.iter().cloned().map(Val::from).collect::<Vec<_>>();
+        self.extend_suggest(&values);
+        self.input_vals.extend(vals.iter().cloned());
+        self.input_stream.extend(values);
+        // Cached results only hold the values read before.
+        self.trie = FilterTrie::default();
+        self.trie.insert(".", self.input_stream.clone());
+
+        let appended = shown.and_then(|shown| {
+            run_filter(filter, &vals, &self.vars, &self.library, self.jq_backend)
+                .ok()
+                .map(|ret| (shown, ret))
+        });
+        let Some((mut results, ret)) = appended else {
+            let input_stream = self.input_stream.clone();
+            self.replace_input(input_stream);
+            return;
+        };
+        if ret.is_empty() {
+            self.trie.insert(filter, results);
+            return;
+        }
+        results.extend(ret);
+        self.trie.insert(filter, results.clone());
+        let position = self.json.stream.position();
+        let mut stream = self.new_stream(results);
+        viewer::restore_folds(&mut stream, &self.json.stream);
+        stream.shift(0, position);
+        self.json.stream = stream;
+        self.matches = None;
+    }
+
+    /// Adds the paths in `values`, which were appended to the input,
+    /// to the auto-completion candidates according to the completion scope.
+    fn extend_suggest(&mut self, values: &[serde_json::Value]) {
+        match self.completion_scope {
+            CompletionScope::First => {}
+            CompletionScope::Union => {
+                let mut seen = self.suggest.iter().cloned().collect::<HashSet<_>>();
+                let room = self
+                    .max_completion_candidates
+                    .map_or(usize::MAX, |max| max.saturating_sub(self.suggest.len()));
+                let paths = path::paths(values)
+                    .map(|segments| path::to_query(&segments))
+                    .filter(|query| seen.insert(query.clone()))
+                    .take(room)
+                    .collect::<Vec<_>>();
+                self.suggest.extend(paths);
+            }
+            CompletionScope::Intersect => {
+                for value in values {
+                    let paths = paths_of(value);
+                    self.suggest.retain(|query| paths.contains(query));
+                }
+            }
+        }
+    }
+
+    /// Scrolls the JSON viewer with the mouse wheel, and moves to and toggles
+    /// the node clicked on, given the row where the interface starts on screen.
+    pub fn on_mouse(&mut self, mouse: &MouseEvent, top: u16) {
+        match mouse.kind {
+            MouseEventKind::ScrollDown => {
+                self.json.stream.forward();
+            }
+            MouseEventKind::ScrollUp => {
+                self.json.stream.backward();
+            }
+            MouseEventKind::Down(MouseButton::Left) => {
+                let row =
+                    (mouse.row.saturating_sub(top) as usize).checked_sub(self.json_pane_top.get());
+                let line = row.and_then(|row| self.json.row_lines.borrow().get(row).copied());
+                if let Some(line) = line {
+                    self.json.stream.move_to_head();
+                    self.json.stream.shift(0, line);
+                    self.json.stream.toggle();
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// Returns how long the event loop may wait for a key before calling
+    /// [`Jnv::on_idle`], or `None` if there is nothing to do meanwhile.
+    pub fn idle_timeout(&self) -> Option<Duration> {
+        let debounce = self
+            .pending_filter
+            .map(|edited| (edited + self.debounce).saturating_duration_since(Instant::now()));
+        let follow = self
+            .follow
+            .as_ref()
+            .map(|_| Duration::from_millis(FOLLOW_POLL_MILLIS));
+        // The oldest of the keys shown with `--show-keys` is the first to go.
+        let key_echo = self.recent_keys.front().map(|(pressed, _)| {
+            (*pressed + Duration::from_millis(KEY_ECHO_DURATION_MILLIS))
+                .saturating_duration_since(Instant::now())
+        });
+        [debounce, follow, key_echo].into_iter().flatten().min()
+    }
+
+    /// Runs the debounced filter if it is due, receives new input with `--follow`
+    /// and drops the keys shown with `--show-keys` that have expired.
+    /// Returns whether anything changed.
+    pub fn on_idle(&mut self) -> bool {
+        let due = self
+            .pending_filter
+            .is_some_and(|edited| edited.elapsed() >= self.debounce);
+        if due {
+            self.run_pending_filter();
+        }
+        let keys = self.recent_keys.len();
+        self.expire_recent_keys();
+        let expired = self.recent_keys.len() != keys;
+        self.receive_input() || due || expired
+    }
+
+    /// Drops the keys shown with `--show-keys` that were pressed too long ago.
+    fn expire_recent_keys(&mut self) {
+        self.recent_keys.retain(|(pressed, _)| {
+            pressed.elapsed() < Duration::from_millis(KEY_ECHO_DURATION_MILLIS)
+        });
+    }
+
+    /// Runs the filter now if it was edited but has not run yet because of `--debounce`.
+    fn run_pending_filter(&mut self) {
+        if self.pending_filter.take().is_some() {
+            let filter = self
+                .filter_editor
+                .after()
+                .texteditor
+                .text_without_cursor()
+                .to_string();
+            self.apply_filter(&filter);
+        }
+    }
+
+    /// Changes how long the filter waits for typing to pause by `delta_millis`.
+    fn adjust_debounce(&mut self, delta_millis: i64) {
+        let millis = (self.debounce.as_millis() as i64 + delta_millis).max(0);
+        self.debounce = Duration::from_millis(millis as u64);
+        self.notify(format!("Filter debounce: {}ms", millis));
+    }
+
+    /// Appends the values read since the last call with `--follow` to the input.
+    /// Returns whether there were any.
+    pub fn receive_input(&mut self) -> bool {
+        let Some(follow) = &self.follow else {
+            return false;
+        };
+        let mut values = vec![];
+        let closed = loop {
+            match follow.try_recv() {
+                Ok(value) => values.push(value),
+                Err(mpsc::TryRecvError::Empty) => break false,
+                Err(mpsc::TryRecvError::Disconnected) => break true,
+            }
+        };
+        if closed {
+            self.follow = None;
+        }
+        if values.is_empty() {
+            return false;
+        }
+
+        let count = values.len();
+        self.append_input(values);
+        self.notify(format!(
+            "Received {} new JSON value(s), {} in total",
+            count,
+            self.input_stream.len()
+        ));
+        true
+    }
+
+    /// Opens a one-line input below the filter editor for `kind`.
+    fn open_line_input(&mut self, kind: LineInput) {
+        let mut editor = self.filter_editor.after().clone();
+        editor.texteditor = Default::default();
+        editor.history = None;
+        editor.prefix = String::from(match kind {
+            LineInput::Goto => "goto ❯ ",
+            LineInput::Save => "save to ❯ ",
+            LineInput::Find => "find ❯ ",
+        });
+        self.line_input = Some((kind, editor));
+        let mut keymap = self.keymap.borrow_mut();
+        self.keymap_before_line_input = keymap.active_key().to_string();
+        keymap.switch("on_line_input");
+    }
+
+    /// Switches between the normal and insert modes of the `vi` keybind preset.
+    fn switch_vi_mode(&mut self, normal: bool) {
+        let (keymap, message) = if normal {
+            ("vi_normal", "-- NORMAL --")
+        } else {
+            ("default", "-- INSERT --")
+        };
+        // Like Vim, leave insert mode on the last character typed rather than after it.
+        if normal {
+            self.filter_editor.after_mut().texteditor.backward();
+        }
+        self.keymap.borrow_mut().switch(keymap);
+        self.notify(message.to_string());
+    }
+
+    /// Closes the one-line input, acting on the entered text unless `cancel` is set.
+    fn close_line_input(&mut self, cancel: bool) {
+        let Some((kind, editor)) = self.line_input.take() else {
+            return;
+        };
+        self.keymap
+            .borrow_mut()
+            .switch(&self.keymap_before_line_input);
+        if cancel {
+            return;
+        }
+        let text = editor.texteditor.text_without_cursor().to_string();
+        match kind {
+            LineInput::Goto => self.goto_path(&text),
+            LineInput::Save => self.save_result(&text),
+            LineInput::Find => self.find(&text),
+        }
+    }
+
+    /// Highlights the text matching `query` in the results and moves to the first match,
+    /// or stops highlighting if `query` is empty.
+    fn find(&mut self, query: &str) {
+        if query.is_empty() {
+            self.json.find = None;
+            return;
+        }
+        match find::pattern(query) {
+            Ok(pattern) => {
+                self.json.find = Some(pattern);
+                self.move_to_match(|matches, _| (!matches.is_empty()).then_some(0));
+            }
+            Err(e) => self.error(format!("Invalid search '{}': {}", query, e)),
+        }
+    }
+
+    /// Moves the JSON viewer cursor to the next (or previous unless `forward`) line
+    /// matching the search.
+    fn find_next(&mut self, forward: bool) {
+        if self.json.find.is_none() {
+            self.warn("Nothing to find (search the results with Ctrl+F)".to_string());
+            return;
+        }
+        self.move_to_match(|matches, position| find::next(matches, position, forward));
+    }
+
+    /// Moves the JSON viewer cursor to the line matching the search that `pick` chooses,
+    /// given the matching lines and the line under the cursor.
+    fn move_to_match(&mut self, pick: impl FnOnce(&[usize], usize) -> Option<usize>) {
+        let Some(pattern) = &self.json.find else {
+            return;
+        };
+        let matches = self.json.find_lines(pattern);
+        let position = self.json.stream.position();
+        let Some(i) = pick(&matches, position) else {
+            self.warn("No match in the results".to_string());
+            return;
+        };
+        self.json.stream.move_to_head();
+        self.json.stream.shift(0, matches[i]);
+        self.notify(format!("Match {} of {}", i + 1, matches.len()));
+    }
+
+    /// Writes the result shown in the JSON viewer to the file at `path`,
+    /// indented like the viewer.
+    fn save_result(&mut self, path: &str) {
+        let text = self
+            .result_values()
+            .iter()
+            .map(|value| {
+                let text = to_output_string(
+                    value,
+                    Some(&self.json.theme.indent_unit()),
+                    self.json.theme.raw_output,
+                );
+                format!("{}\n", text)
+            })
+            .collect::<String>();
+        match fs::write(path, text) {
+            Ok(()) => self.notify(format!("Saved result to {}", path)),
+            Err(e) => self.error(format!("Failed to save result to '{}': {}", path, e)),
+        }
+    }
+
+    /// Finds the node at `query` in the results: a jq path such as `.items[0]`,
+    /// or a JSON Pointer such as `/items/0` when it starts with `/`.
+    /// Returns the index of the JSON value holding it and its path within that value.
+    fn find_path(&self, query: &str) -> Result<(usize, JsonPath), String> {
+        let stream = &self.json.stream;
+        if query.starts_with('/') {
+            let segment = |i: usize| query.split('/').nth(i + 1).unwrap_or_default().to_string();
+            let tokens = path::parse_pointer(query).map_err(|i| {
+                format!(
+                    "Invalid escape in segment {} ('{}') of '{}'",
+                    i + 1,
+                    segment(i),
+                    query
+                )
+            })?;
+            // Report the segment where the value that resolves furthest fails.
+            let mut failed = 0;
+            for root in 0..stream.roots().len() {
+                match stream
+                    .get_root(root)
+                    .map(|node| path::resolve_pointer(node, &tokens))
+                {
+                    Some(Ok(path)) => return Ok((root, path)),
+                    Some(Err(i)) => failed = failed.max(i),
+                    None => {}
+                }
+            }
+            return Err(format!(
+                "No node at segment {} ('{}') of '{}' in the results",
+                failed + 1,
+                segment(failed),
+                query
+            ));
+        }
+
+        let path = path::parse_query(query)
+            .ok_or_else(|| format!("'{}' is not a path to a single node", query))?;
+        let root = (0..stream.roots().len())
+            .find(|&i| {
+                stream
+                    .get_root(i)
+                    .and_then(|node| node.get(&path))
+                    .is_some()
+            })
+            .ok_or_else(|| format!("No node at '{}' in the results", query))?;
+        Ok((root, path))
+    }
+
+    /// Moves the JSON viewer cursor to the node at `query` in the results,
+    /// expanding its collapsed ancestors.
+    fn goto_path(&mut self, query: &str) {
+        let (root, path) = match self.find_path(query) {
+            Ok(found) => found,
+            Err(e) => {
+                self.error(e);
+                return;
+            }
+        };
+        let stream = &mut self.json.stream;
+
+        for i in 0..path.len() {
+            let collapsed = matches!(
+                stream
+                    .get_root(root)
+                    .and_then(|node| node.get(&path[..i].to_vec())),
+                Some(
+                    JsonNode::Object {
+                        children_visible: false,
+                        ..
+                    } | JsonNode::Array {
+                        children_visible: false,
+                        ..
+                    }
+                )
+            );
+            if collapsed && move_to_path(stream, root, &path[..i]) {
+                stream.toggle();
+            }
+        }
+        move_to_path(stream, root, &path);
+        self.notify(format!("Moved to '{}'", path::to_query(&path)));
+    }
+
+    /// Shows the input data collapsed everywhere except along the path
+    /// that the current filter points to, with the cursor on that node.
+    /// When the filter is only partially typed, the longest prefix that
+    /// exists in the input is used instead.
+    fn show_filter_context(&mut self) {
+        let filter = self
+            .filter_editor
+            .after()
+            .texteditor
+            .text_without_cursor()
+            .to_string();
+        let Some(path) = path::parse_query(&filter) else {
+            self.warn(format!(
+                "JSON query ('{}') is not a path to a single node",
+                &filter
+            ));
+            return;
+        };
+
+        // Pick the root in which the longest prefix of the path resolves.
+        let Some((root, depth)) = self
+            .input_stream
+            .iter()
+            .map(|value| {
+                let mut value = value;
+                path.iter()
+                    .take_while(|segment| {
+                        let next = match segment {
+                            JsonPathSegment::Key(key) => value.get(key),
+                            JsonPathSegment::Index(index) => value.get(index),
+                        };
+                        next.map(|next| value = next).is_some()
+                    })
+                    .count()
+            })
+            .enumerate()
+            .max_by(|(i, a), (j, b)| a.cmp(b).then(j.cmp(i)))
+        else {
+            return;
+        };
+
+        self.hint_message.reset_after_to_init();
+        self.diff_view = None;
+        self.matches = None;
+        self.filter_error = None;
+        self.json.stale = false;
+        let mut stream = self.new_stream_expanded_to(self.input_stream.clone(), Some(0));
+        // With --max-result-lines, show the page of the root instead of the first one.
+        let root = match self
+            .result_pages
+            .iter()
+            .position(|page| page.contains(&root))
+        {
+            Some(page) => {
+                let range = self.result_pages[page].clone();
+                if page != self.result_page {
+                    self.result_page = page;
+                    stream = collapsed_stream(
+                        self.paged_result[range.clone()].to_vec(),
+                        Some(0),
+                        self.collapse_arrays,
+                        self.collapse_objects,
+                    );
+                }
+                root - range.start
+            }
+            None => root,
+        };
+        for i in 0..=depth {
+            if move_to_path(&mut stream, root, &path[..i]) {
+                stream.toggle();
+            }
+        }
+        self.json.stream = stream;
+        self.notify(format!(
+            "Showing '{}' in the input data",
+            path::to_query(&path[..depth])
+        ));
+    }
+}
+
+impl promkit::Finalizer for Jnv {
+    type Return = String;
+
+    fn finalize(&self) -> anyhow::Result<Self::Return> {
+        Ok(self
+            .filter_editor
+            .after()
+            .texteditor
+            .text_without_cursor()
+            .to_string())
+    }
+}
+
+impl Renderer for Jnv {
+    fn create_panes(&self, width: u16, height: u16) -> Vec<Pane> {
+        let mut panes = vec![];
+        if self.editor_visible {
+            if let SuggestionPlacement::Above = self.suggestion_placement {
+                panes.push(self.suggestions.create_pane(width, height));
+            }
+            panes.push(self.create_filter_editor_pane(width, height));
+        }
+        if let Some((_, editor)) = &self.line_input {
+            panes.push(editor.create_pane(width, height));
+        }
+        self.hint_pane_top
+            .set(panes.iter().map(Pane::visible_row_count).sum());
+        panes.push(self.hint_message.create_pane(width, height));
+        if !self.stage_previews.is_empty() {
+            let style = StyleBuilder::new().fgc(Color::DarkCyan).build();
+            panes.push(Pane::new(
+                self.stage_previews
+                    .iter()
+                    .flat_map(|preview| {
+                        // Truncate each preview to a single line.
+                        StyledGraphemes::from_str(preview, style)
+                            .matrixify(width as usize, 1, 0)
+                            .0
+                    })
+                    .collect(),
+                0,
+            ));
+        }
+        if self.editor_visible {
+            if let SuggestionPlacement::Below = self.suggestion_placement {
+                panes.push(self.suggestions.create_pane(width, height));
+            }
+            if let Some(separator) = &self.pane_separator {
+                // Repeat the character to fill the terminal width.
+                let repeat =
+                    width as usize / StyledGraphemes::from(&separator.text).widths().max(1);
+                panes.push(
+                    text::State {
+                        text: separator.text.repeat(repeat),
+                        style: separator.style,
+                    }
+                    .create_pane(width, height),
+                );
+            }
+        }
+        if let Some(error) = &self.filter_error {
+            // Parse errors list every expected token, so keep them to two rows.
+            let style = StyleBuilder::new().fgc(Color::Red).build();
+            panes.push(Pane::new(
+                StyledGraphemes::from_str(format!("Error: {}", error), style)
+                    .matrixify(width as usize, 2, 0)
+                    .0,
+                0,
+            ));
+        }
+        match &self.diff_view {
+            Some(lines) => panes.push(Pane::new(
+                lines
+                    .iter()
+                    .flat_map(|line| line.matrixify(width as usize, height as usize, 0).0)
+                    .collect(),
+                0,
+            )),
+            None => {
+                self.json_pane_top
+                    .set(panes.iter().map(Pane::visible_row_count).sum());
+                panes.push(self.json.create_pane(width, height))
+            }
+        }
+        if self.status_bar {
+            panes.push(self.create_status_bar_pane(width));
+        }
+        if self.show_keys {
+            panes.push(
+                text::State {
+                    text: self
+                        .recent_keys
+                        .iter()
+                        .filter(|(pressed, _)| {
+                            pressed.elapsed() < Duration::from_millis(KEY_ECHO_DURATION_MILLIS)
+                        })
+                        .map(|(_, key)| key.as_str())
+                        .collect::<Vec<_>>()
+                        .join(" "),
+                    style: StyleBuilder::new()
+                        .fgc(Color::Magenta)
+                        .attrs(Attributes::from(Attribute::Bold))
+                        .build(),
+                }
+                .create_pane(width, height),
+            );
+        }
+        panes
+    }
+
+    fn evaluate(&mut self, event: &Event) -> anyhow::Result<PromptSignal> {
+        if self.show_keys {
+            if let Event::Key(key) = event {
+                self.expire_recent_keys();
+                if self.recent_keys.len() == KEY_ECHO_LENGTH {
+                    self.recent_keys.pop_front();
+                }
+                self.recent_keys
+                    .push_back((Instant::now(), keymap::format_key_event(key)));
+            }
+        }
+
+        // Keys acting on the results need those of the current filter.
+        if !keymap::edits_filter(event) {
+            self.run_pending_filter();
+        }
+
+        let keymap = *self.keymap.borrow_mut().get();
+        let signal = keymap(event, self);
+        let filter = self
+            .filter_editor
+            .after()
+            .texteditor
+            .text_without_cursor()
+            .to_string();
+        let before = std::mem::replace(&mut self.last_filter, filter.clone());
+
+        // Check if the query has changed
+        if filter != before {
+            let is_insert = matches!(
+                event,
+                Event::Key(KeyEvent {
+                    code: KeyCode::Char(_),
+                    modifiers: KeyModifiers::NONE | KeyModifiers::SHIFT,
+                    ..
+                })
+            );
+            self.edits.record(&before, is_insert);
+            if self.debounce.is_zero() {
+                self.apply_filter(&filter);
+            } else {
+                self.pending_filter = Some(Instant::now());
+            }
+        } else {
+            self.edits.end_unit();
         }
         signal
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Starts a session on `input` with default settings, without a terminal.
+    fn jnv(input: Vec<serde_json::Value>, options: impl FnOnce(&mut Options)) -> Jnv {
+        let mut settings = Options {
+            json_expand_depth: None,
+            no_hint: false,
+            input_loader: None,
+            pane_separator: None,
+            suggestion_placement: SuggestionPlacement::Below,
+            show_keys: false,
+            status_bar: false,
+            pipe_preview: false,
+            max_completion_candidates: None,
+            input_paths: Vec::new(),
+            slurp: false,
+            input_sources: Vec::new(),
+            input_truncated: false,
+            collapse_arrays: false,
+            collapse_objects: false,
+            data_uri_mime: "application/json".to_string(),
+            wrap_templates: Vec::new(),
+            read_only: false,
+            newline_mode: NewlineMode::Separate,
+            palette: 0,
+            completion_scope: CompletionScope::Union,
+            allow_open_url: false,
+            compact_copy: false,
+            clipboard_timeout: Duration::ZERO,
+            colors: Vec::new(),
+            vars: Vec::new(),
+            library: String::new(),
+            search_mode: SearchMode::Prefix,
+            suggestion_sort: SuggestionSort::Relevance,
+            follow: None,
+            jq_backend: JqBackend::Embedded,
+            debounce: Duration::ZERO,
+            keybind_preset: KeybindPreset::Default,
+            chord_timeout: Duration::ZERO,
+            elapsed_threshold: Duration::MAX,
+            persist_pins: false,
+            max_result_lines: None,
+            stats: None,
+        };
+        options(&mut settings);
+        let style = ContentStyle::default();
+        Jnv::new(
+            input,
+            text_editor::State {
+                texteditor: Default::default(),
+                history: None,
+                prefix: String::new(),
+                mask: None,
+                prefix_style: style,
+                active_char_style: style,
+                inactive_char_style: style,
+                edit_mode: Default::default(),
+                word_break_chars: HashSet::new(),
+                lines: None,
+            },
+            text::State {
+                text: String::new(),
+                style,
+            },
+            listbox::State {
+                listbox: listbox::Listbox::from_iter(Vec::<String>::new()),
+                cursor: String::new(),
+                active_item_style: None,
+                inactive_item_style: None,
+                lines: None,
+            },
+            JsonTheme {
+                curly_brackets_style: style,
+                square_brackets_style: style,
+                key_style: style,
+                string_value_style: style,
+                number_value_style: style,
+                boolean_value_style: style,
+                null_value_style: style,
+                active_item_attribute: Attribute::Bold,
+                inactive_item_attribute: Attribute::Dim,
+                lines: None,
+                indent: 2,
+                indent_char: ' ',
+                number_separator: None,
+                scrollbar: false,
+                max_key_width: None,
+                max_value_width: None,
+                ellipsis: Ellipsis::End,
+                line_numbers: None,
+                line_number_style: style,
+                tree_guides: false,
+                tree_guide_style: style,
+                wrap_values: false,
+                raw_output: false,
+                keys_only: false,
+                match_style: style,
+            },
+            settings,
+        )
+    }
+
+    mod apply_filter {
+        use super::*;
+
+        #[test]
+        fn test_error() {
+            let mut jnv = jnv(vec![serde_json::json!({"a": 1})], |_| {});
+            jnv.apply_filter(".a");
+            assert_eq!(None, jnv.filter_error);
+            jnv.apply_filter(".a | error(\"x\")");
+            assert_eq!(Some("x".to_string()), jnv.filter_error);
+            assert!(jnv.json.stale);
+        }
+    }
+
+    mod benchmark {
+        use super::*;
+
+        /// Times the filters run on each edit against a large input, compiled once
+        /// and run on the input converted to jaq values at startup, against compiling
+        /// them and converting the input again for every value as before.
+        /// Run with `cargo test --release -- --ignored --nocapture benchmark`.
+        #[test]
+        #[ignore]
+        fn test_run_filter() {
+            let values = (0..1000)
+                .map(|i| serde_json::json!({"id": i, "name": format!("item{}", i), "tags": ["a", "b"]}))
+                .collect::<Vec<_>>();
+            let queries = [
+                ".",
+                ".id",
+                ".tags[0]",
+                ".name | ascii_upcase",
+                "select(.id % 2 == 0)",
+            ];
+
+            let started = Instant::now();
+            for query in queries {
+                for value in &values {
+                    run_query(
+                        query,
+                        std::slice::from_ref(value),
+                        &[],
+                        "",
+                        JqBackend::Embedded,
+                    )
+                    .unwrap();
+                }
+            }
+            let per_value = started.elapsed();
+
+            let input_vals = values.iter().cloned().map(Val::from).collect::<Vec<_>>();
+            let started = Instant::now();
+            for query in queries {
+                run_filter(query, &input_vals, &[], "", JqBackend::Embedded).unwrap();
+            }
+            let once = started.elapsed();
+
+            println!(
+                "{} filters on {} values: {:?} compiled per value, {:?} compiled once",
+                queries.len(),
+                values.len(),
+                per_value,
+                once
+            );
+            assert!(once < per_value);
+        }
+    }
+
+    mod collapsed_stream {
+        use super::*;
+
+        #[test]
+        fn test_arrays_and_objects() {
+            let values = vec![
+                serde_json::json!({"a": [1, {"b": [2]}], "c": {"d": {}}}),
+                serde_json::json!([[3], {"e": 4}]),
+                serde_json::json!(5),
+            ];
+            for depth in [None, Some(0), Some(1), Some(2)] {
+                let mut toggled = JsonStream::new(values.clone(), depth);
+                collapse_kinds(&mut toggled, true, true);
+                assert_eq!(
+                    toggled.flatten_kinds(),
+                    collapsed_stream(values.clone(), depth, true, true).flatten_kinds()
+                );
+            }
+        }
+    }
+
+    mod cycle_matches {
+        use super::*;
+
+        #[test]
+        fn test_empty_filter() {
+            let mut jnv = jnv(vec![serde_json::json!(1), serde_json::json!(2)], |_| {});
+            jnv.filter_editor.after_mut().texteditor.replace("  ");
+            jnv.cycle_matches(true);
+            jnv.cycle_matches(true);
+            assert_eq!(Some((vec![0, 1], 1)), jnv.matches);
+            assert_eq!(vec![serde_json::json!(2)], jnv.json.values());
+        }
+    }
+
+    mod evaluate {
+        use super::*;
+
+        fn key(code: KeyCode, modifiers: KeyModifiers) -> Event {
+            Event::Key(KeyEvent::new(code, modifiers))
+        }
+
+        /// Events handled before the next frame is drawn still record their own edits.
+        #[test]
+        fn test_events_within_a_frame() {
+            let mut jnv = jnv(vec![serde_json::json!({"a": 1})], |_| {});
+            for event in [
+                key(KeyCode::Char('.'), KeyModifiers::NONE),
+                key(KeyCode::Char('a'), KeyModifiers::NONE),
+                key(KeyCode::Backspace, KeyModifiers::NONE),
+                key(KeyCode::Char('z'), KeyModifiers::CONTROL),
+            ] {
+                jnv.evaluate(&event).unwrap();
+            }
+            assert_eq!(
+                ".a",
+                jnv.filter_editor
+                    .after()
+                    .texteditor
+                    .text_without_cursor()
+                    .to_string()
+            );
+        }
+    }
+
+    mod jq_command {
+        use super::*;
+
+        #[test]
+        fn test() {
+            let mut jnv = jnv(vec![serde_json::json!([])], |options| {
+                options.input_paths = vec![PathBuf::from("a.json"), PathBuf::from("my b.json")];
+                options.slurp = true;
+                options.vars = vec![("name".to_string(), serde_json::json!("x"))];
+            });
+            jnv.json.theme.raw_output = true;
+            jnv.filter_editor
+                .after_mut()
+                .texteditor
+                .replace(".[] | .name");
+            assert_eq!(
+                "jq -s -r --arg name x '.[] | .name' a.json 'my b.json'",
+                jnv.jq_command()
+            );
+        }
+    }
+
+    mod run_query {
+        use super::*;
+
+        fn run(query: &str, input: serde_json::Value) -> Result<Vec<serde_json::Value>> {
+            run_query(query, &[input], &[], "", JqBackend::Embedded)
+        }
+
+        #[test]
+        fn test() {
+            assert_eq!(
+                vec![serde_json::json!(1)],
+                run(".a", serde_json::json!({"a": 1})).unwrap()
+            );
+        }
+
+        #[test]
+        fn test_undefined_filter() {
+            let e = run("foo(1)", serde_json::json!({"a": 1})).unwrap_err();
+            assert_eq!("undefined filter 'foo(1)'", e.to_string());
+        }
+
+        #[test]
+        fn test_undefined_variable() {
+            let e = run("$nope", serde_json::json!({"a": 1})).unwrap_err();
+            assert_eq!("undefined variable '$nope'", e.to_string());
+        }
+
+        #[test]
+        fn test_runtime_error() {
+            let e = run(".a | error(\"boom\")", serde_json::json!({"a": 1})).unwrap_err();
+            assert_eq!("boom", e.to_string());
+            assert!(run(".a", serde_json::json!(5)).is_err());
+        }
+
+        #[test]
+        fn test_library_error() {
+            let run = |query, library| {
+                run_query(query, &[], &[], library, JqBackend::Embedded)
+                    .unwrap_err()
+                    .to_string()
+            };
+            assert_eq!(
+                "undefined filter 'nope' (in --library)",
+                run(".", "def f: nope;")
+            );
+            assert_eq!("undefined filter 'nope'", run("nope", "def f: 1;"));
+        }
+    }
+
+    mod create_status_bar_pane {
+        use super::*;
+
+        #[test]
+        fn test_whitespace_filter() {
+            let mut jnv = jnv(vec![serde_json::json!(1)], |_| {});
+            jnv.filter_editor.after_mut().texteditor.replace("  ");
+            jnv.apply_filter("  ");
+            let rows = screenshot::compose(&[jnv.create_status_bar_pane(40)], 1);
+            assert!(rows[0].contains("│ OK"), "{:?}", rows);
+        }
+    }
+
+    mod stream_query {
+        use super::*;
+
+        #[test]
+        fn test() {
+            let inputs = [
+                serde_json::json!({"a": [1, 2]}),
+                serde_json::json!({"a": [3]}),
+            ];
+            let mut results = Vec::new();
+            stream_query(".a[]", &inputs, &[], "", JqBackend::Embedded, |value| {
+                results.push(value);
+                Ok(())
+            })
+            .unwrap();
+            assert_eq!(
+                run_query(".a[]", &inputs, &[], "", JqBackend::Embedded).unwrap(),
+                results
+            );
+        }
+
+        #[test]
+        fn test_stops_at_error() {
+            let inputs = [serde_json::json!(1), serde_json::json!(2)];
+            let mut results = Vec::new();
+            let e = stream_query(".", &inputs, &[], "", JqBackend::Embedded, |value| {
+                results.push(value);
+                Err(io::ErrorKind::BrokenPipe.into())
+            })
+            .unwrap_err();
+            assert_eq!(
+                io::ErrorKind::BrokenPipe,
+                e.downcast::<io::Error>().unwrap().kind()
+            );
+            assert_eq!(vec![serde_json::json!(1)], results);
+        }
+    }
+
+    mod show_filter_context {
+        use super::*;
+
+        #[test]
+        fn test_resets_result_state() {
+            let mut jnv = jnv(
+                vec![
+                    serde_json::json!({"a": 1}),
+                    serde_json::json!({"b": {"c": 2}}),
+                ],
+                |options| options.max_result_lines = Some(3),
+            );
+            jnv.filter_editor.after_mut().texteditor.replace(".b.c");
+            jnv.diff_view = Some(Vec::new());
+            jnv.json.stale = true;
+            jnv.show_filter_context();
+            assert!(jnv.diff_view.is_none());
+            assert!(!jnv.json.stale);
+            assert_eq!(vec![0..1, 1..2], jnv.result_pages);
+            assert_eq!(1, jnv.result_page);
+            assert_eq!(
+                Some(vec![
+                    JsonPathSegment::Key("b".to_string()),
+                    JsonPathSegment::Key("c".to_string())
+                ]),
+                jnv.json.stream.current_root_and_path_from_root().1
+            );
+        }
+    }
+
+    mod shift_line_numbers {
+        use super::*;
+
+        #[test]
+        fn test() {
+            let message = "syntax error at <top-level>, line 3:";
+            assert_eq!(
+                "syntax error at <top-level>, line 1:",
+                shift_line_numbers(message, "def f: 1;\ndef g: 2;")
+            );
+            assert_eq!(message, shift_line_numbers(message, ""));
+        }
+
+        #[test]
+        fn test_in_library() {
+            assert_eq!(
+                "error at <top-level>, line 1 of --library:",
+                shift_line_numbers("error at <top-level>, line 1:", "def f: nope;")
+            );
+        }
+    }
+}
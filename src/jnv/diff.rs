@@ -0,0 +1,114 @@
+/// Maximum number of cells in the table used to compute a diff.
+/// Larger differences are shown as a whole block removed and added.
+const MAX_TABLE_SIZE: usize = 4_000_000;
+
+/// A line of a diff between two texts.
+#[derive(Debug, PartialEq)]
+pub enum Line<'a> {
+    Same(&'a str),
+    Removed(&'a str),
+    Added(&'a str),
+}
+
+/// Computes a line-based diff turning `old` into `new`
+/// using the longest common subsequence of lines.
+pub fn diff<'a>(old: &[&'a str], new: &[&'a str]) -> Vec<Line<'a>> {
+    let prefix = old.iter().zip(new).take_while(|(a, b)| a == b).count();
+    let suffix = old[prefix..]
+        .iter()
+        .rev()
+        .zip(new[prefix..].iter().rev())
+        .take_while(|(a, b)| a == b)
+        .count();
+    let (old_rest, new_rest) = (
+        &old[prefix..old.len() - suffix],
+        &new[prefix..new.len() - suffix],
+    );
+
+    let mut ret = old[..prefix]
+        .iter()
+        .map(|line| Line::Same(line))
+        .collect::<Vec<_>>();
+
+    if (old_rest.len() + 1) * (new_rest.len() + 1) > MAX_TABLE_SIZE {
+        ret.extend(old_rest.iter().map(|line| Line::Removed(line)));
+        ret.extend(new_rest.iter().map(|line| Line::Added(line)));
+    } else {
+        // lcs[i][j] is the length of the longest common subsequence
+        // of old_rest[i..] and new_rest[j..].
+        let mut lcs = vec![vec![0usize; new_rest.len() + 1]; old_rest.len() + 1];
+        for i in (0..old_rest.len()).rev() {
+            for j in (0..new_rest.len()).rev() {
+                lcs[i][j] = if old_rest[i] == new_rest[j] {
+                    lcs[i + 1][j + 1] + 1
+                } else {
+                    lcs[i + 1][j].max(lcs[i][j + 1])
+                };
+            }
+        }
+
+        let (mut i, mut j) = (0, 0);
+        while i < old_rest.len() || j < new_rest.len() {
+            if i < old_rest.len() && j < new_rest.len() && old_rest[i] == new_rest[j] {
+                ret.push(Line::Same(old_rest[i]));
+                i += 1;
+                j += 1;
+            } else if i < old_rest.len() && (j == new_rest.len() || lcs[i + 1][j] >= lcs[i][j + 1])
+            {
+                ret.push(Line::Removed(old_rest[i]));
+                i += 1;
+            } else {
+                ret.push(Line::Added(new_rest[j]));
+                j += 1;
+            }
+        }
+    }
+
+    ret.extend(
+        old[old.len() - suffix..]
+            .iter()
+            .map(|line| Line::Same(line)),
+    );
+    ret
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    mod diff {
+        use super::*;
+
+        #[test]
+        fn test_identical() {
+            assert_eq!(
+                vec![Line::Same("a"), Line::Same("b")],
+                diff(&["a", "b"], &["a", "b"])
+            );
+        }
+
+        #[test]
+        fn test_changes() {
+            assert_eq!(
+                vec![
+                    Line::Same("["),
+                    Line::Removed("  1,"),
+                    Line::Same("  2,"),
+                    Line::Added("  4,"),
+                    Line::Same("  3"),
+                    Line::Same("]"),
+                ],
+                diff(
+                    &["[", "  1,", "  2,", "  3", "]"],
+                    &["[", "  2,", "  4,", "  3", "]"]
+                )
+            );
+        }
+
+        #[test]
+        fn test_empty() {
+            assert_eq!(vec![Line::Added("1")], diff(&[], &["1"]));
+            assert_eq!(vec![Line::Removed("1")], diff(&["1"], &[]));
+        }
+    }
+}
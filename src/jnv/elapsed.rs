@@ -0,0 +1,44 @@
+use std::{
+    io::{self, Write},
+    sync::mpsc::{Receiver, RecvTimeoutError},
+    time::{Duration, Instant},
+};
+
+use promkit::crossterm::{
+    cursor::{MoveTo, RestorePosition, SavePosition},
+    queue,
+    style::{ContentStyle, PrintStyledContent},
+    terminal::{Clear, ClearType},
+};
+
+/// How often the time shown for a running filter is updated.
+const TICK: Duration = Duration::from_millis(100);
+
+/// Shows on terminal `row` how long a filter has been running once that exceeds
+/// `threshold`, updating it until something is sent on `done` or its sender is dropped.
+/// The filter blocks the interface meanwhile, so this draws on the terminal directly.
+pub fn show(row: u16, threshold: Duration, style: ContentStyle, done: Receiver<()>) {
+    let started = Instant::now();
+    let mut wait = threshold;
+    while let Err(RecvTimeoutError::Timeout) = done.recv_timeout(wait) {
+        // Keep running the filter even if the terminal cannot be written to.
+        let _ = draw(row, started.elapsed(), style);
+        wait = TICK;
+    }
+}
+
+fn draw(row: u16, elapsed: Duration, style: ContentStyle) -> io::Result<()> {
+    let mut stdout = io::stdout().lock();
+    queue!(
+        stdout,
+        SavePosition,
+        MoveTo(0, row),
+        Clear(ClearType::CurrentLine),
+        PrintStyledContent(style.apply(format!(
+            "JSON query has been running for {:.1}s",
+            elapsed.as_secs_f64()
+        ))),
+        RestorePosition,
+    )?;
+    stdout.flush()
+}
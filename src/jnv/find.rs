@@ -0,0 +1,110 @@
+use anyhow::{anyhow, Result};
+use promkit::{crossterm::style::ContentStyle, grapheme::StyledGraphemes};
+use regex::{Regex, RegexBuilder};
+
+/// Compiles the text searched for in the results:
+/// a regular expression if enclosed in slashes (e.g. `/id_\d+/`), a literal otherwise.
+/// Either way, the search ignores case unless the text has an uppercase letter.
+pub fn pattern(query: &str) -> Result<Regex> {
+    let expr = match query
+        .strip_prefix('/')
+        .and_then(|query| query.strip_suffix('/'))
+    {
+        Some(expr) => expr.to_string(),
+        None => regex::escape(query),
+    };
+    RegexBuilder::new(&expr)
+        .case_insensitive(!query.chars().any(char::is_uppercase))
+        .build()
+        // Only the last line of the error tells what is wrong, the others show where.
+        .map_err(|e| anyhow!("{}", e.to_string().lines().last().unwrap_or_default()))
+}
+
+/// Returns the index in `matches` (the lines with a match, in order)
+/// of the next match after the line `position`, or of the previous one
+/// before it unless `forward`, wrapping around at the ends.
+pub fn next(matches: &[usize], position: usize, forward: bool) -> Option<usize> {
+    if matches.is_empty() {
+        return None;
+    }
+    Some(if forward {
+        matches
+            .iter()
+            .position(|&line| line > position)
+            .unwrap_or(0)
+    } else {
+        matches
+            .iter()
+            .rposition(|&line| line < position)
+            .unwrap_or(matches.len() - 1)
+    })
+}
+
+/// Applies `style` to the parts of `row` matching `pattern`.
+pub fn highlight(
+    mut row: StyledGraphemes,
+    pattern: &Regex,
+    style: ContentStyle,
+) -> StyledGraphemes {
+    let text = row.to_string();
+    // Graphemes are single characters, so map the byte offsets of the matches to char indices.
+    let offsets = text.char_indices().map(|(i, _)| i).collect::<Vec<_>>();
+    for found in pattern.find_iter(&text) {
+        let start = offsets.partition_point(|&i| i < found.start());
+        let end = offsets.partition_point(|&i| i < found.end());
+        for g in row.iter_mut().take(end).skip(start) {
+            g.apply_style(style);
+        }
+    }
+    row
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    mod pattern {
+        use super::*;
+
+        #[test]
+        fn test_literal() {
+            let pattern = pattern("a.b").unwrap();
+            assert!(pattern.is_match("xA.Bx"));
+            assert!(!pattern.is_match("axb"));
+        }
+
+        #[test]
+        fn test_regex() {
+            let pattern = pattern(r"/id_\d+/").unwrap();
+            assert!(pattern.is_match(r#""id_42""#));
+            assert!(!pattern.is_match("id_x"));
+            assert!(super::pattern("/(/").is_err());
+        }
+
+        #[test]
+        fn test_smart_case() {
+            assert!(!pattern("Name").unwrap().is_match("name"));
+        }
+    }
+
+    mod next {
+        use super::*;
+
+        #[test]
+        fn test_forward() {
+            assert_eq!(Some(1), next(&[2, 5, 9], 2, true));
+            assert_eq!(Some(0), next(&[2, 5, 9], 9, true));
+        }
+
+        #[test]
+        fn test_backward() {
+            assert_eq!(Some(1), next(&[2, 5, 9], 9, false));
+            assert_eq!(Some(2), next(&[2, 5, 9], 2, false));
+        }
+
+        #[test]
+        fn test_none() {
+            assert_eq!(None, next(&[], 0, true));
+        }
+    }
+}
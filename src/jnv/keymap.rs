@@ -1,12 +1,85 @@
+use std::time::Instant;
+
 use promkit::{
     crossterm::event::{Event, KeyCode, KeyEvent, KeyEventKind, KeyEventState, KeyModifiers},
     listbox::Listbox,
     text_editor, PromptSignal,
 };
 
+use super::LineInput;
+
+/// How much Alt+= and Alt+- change the debounce of the filter by.
+const DEBOUNCE_STEP_MILLIS: i64 = 100;
+
 pub type Keymap = fn(&Event, &mut crate::jnv::Jnv) -> anyhow::Result<PromptSignal>;
 
+/// Formats a key event for display, e.g. `Ctrl+C`, `Alt+B` or `Enter`.
+pub fn format_key_event(event: &KeyEvent) -> String {
+    let code = match event.code {
+        KeyCode::Char(' ') => "Space".to_string(),
+        KeyCode::Char(ch) => ch.to_string(),
+        KeyCode::Up => "↑".to_string(),
+        KeyCode::Down => "↓".to_string(),
+        KeyCode::Left => "←".to_string(),
+        KeyCode::Right => "→".to_string(),
+        KeyCode::Esc => "Esc".to_string(),
+        code => format!("{:?}", code),
+    };
+    let mut modifiers = vec![];
+    if event.modifiers.contains(KeyModifiers::CONTROL) {
+        modifiers.push("Ctrl");
+    }
+    if event.modifiers.contains(KeyModifiers::ALT) {
+        modifiers.push("Alt");
+    }
+    modifiers.push(&code);
+    modifiers.join("+")
+}
+
+/// Returns whether `event` would move the cursor in or modify the filter.
+pub fn edits_filter(event: &Event) -> bool {
+    matches!(
+        event,
+        Event::Key(KeyEvent {
+            code: KeyCode::Tab | KeyCode::Left | KeyCode::Right | KeyCode::Backspace,
+            modifiers: KeyModifiers::NONE,
+            kind: KeyEventKind::Press,
+            state: KeyEventState::NONE,
+        }) | Event::Key(KeyEvent {
+            code: KeyCode::Char('a' | 'e' | 'u' | 'w' | 'z' | 'y'),
+            modifiers: KeyModifiers::CONTROL,
+            kind: KeyEventKind::Press,
+            state: KeyEventState::NONE,
+        }) | Event::Key(KeyEvent {
+            code: KeyCode::Char('x'),
+            modifiers: KeyModifiers::CONTROL,
+            kind: KeyEventKind::Press,
+            state: KeyEventState::NONE,
+        }) | Event::Key(KeyEvent {
+            code: KeyCode::Enter,
+            modifiers: KeyModifiers::ALT,
+            kind: KeyEventKind::Press,
+            state: KeyEventState::NONE,
+        }) | Event::Key(KeyEvent {
+            code: KeyCode::Char('b' | 'f' | 'd' | 'i' | 'h' | ',' | '.' | '1'..='9'),
+            modifiers: KeyModifiers::ALT,
+            kind: KeyEventKind::Press,
+            state: KeyEventState::NONE,
+        }) | Event::Key(KeyEvent {
+            code: KeyCode::Char(_),
+            modifiers: KeyModifiers::NONE | KeyModifiers::SHIFT,
+            kind: KeyEventKind::Press,
+            state: KeyEventState::NONE,
+        })
+    )
+}
+
 pub fn default(event: &Event, jnv: &mut crate::jnv::Jnv) -> anyhow::Result<PromptSignal> {
+    // Only the keys that do not edit the filter work while it is read-only.
+    if jnv.read_only() && edits_filter(event) {
+        return Ok(PromptSignal::Continue);
+    }
+
     let filter_editor = jnv.filter_editor.after_mut();
 
     match event {
@@ -17,9 +90,13 @@ pub fn default(event: &Event, jnv: &mut crate::jnv::Jnv) -> anyhow::Result<Promp
             state: KeyEventState::NONE,
         }) => {
             let query = filter_editor.texteditor.text_without_cursor().to_string();
-            if let Some(mut candidates) = jnv.suggest.prefix_search(query) {
-                candidates.sort_by(|a, b| a.len().cmp(&b.len()).then_with(|| a.cmp(b)));
-
+            let mut candidates = super::search::search(&jnv.suggest, &query, jnv.search_mode);
+            super::search::sort(&mut candidates, jnv.suggestion_sort, &jnv.suggestion_uses);
+            if !candidates.is_empty() {
+                // The only candidate is taken as it is, which counts as picking it.
+                if let [candidate] = candidates.as_slice() {
+                    *jnv.suggestion_uses.entry(candidate.clone()).or_default() += 1;
+                }
                 jnv.suggestions.listbox = Listbox::from_iter(candidates);
                 filter_editor
                     .texteditor
@@ -36,6 +113,14 @@ pub fn default(event: &Event, jnv: &mut crate::jnv::Jnv) -> anyhow::Result<Promp
             state: KeyEventState::NONE,
         }) => return Ok(PromptSignal::Quit),
 
+        // Quit, writing out the result
+        Event::Key(KeyEvent {
+            code: KeyCode::Char('o'),
+            modifiers: KeyModifiers::CONTROL,
+            kind: KeyEventKind::Press,
+            state: KeyEventState::NONE,
+        }) => return Ok(jnv.accept()),
+
         // Move cursor.
         Event::Key(KeyEvent {
             code: KeyCode::Left,
@@ -117,6 +202,20 @@ pub fn default(event: &Event, jnv: &mut crate::jnv::Jnv) -> anyhow::Result<Promp
             .texteditor
             .erase_to_next_nearest(&filter_editor.word_break_chars),
 
+        // Undo/redo edits.
+        Event::Key(KeyEvent {
+            code: KeyCode::Char('z'),
+            modifiers: KeyModifiers::CONTROL,
+            kind: KeyEventKind::Press,
+            state: KeyEventState::NONE,
+        }) => jnv.undo(),
+        Event::Key(KeyEvent {
+            code: KeyCode::Char('y'),
+            modifiers: KeyModifiers::CONTROL,
+            kind: KeyEventKind::Press,
+            state: KeyEventState::NONE,
+        }) => jnv.redo(),
+
         // Move up.
         Event::Key(KeyEvent {
             code: KeyCode::Up,
@@ -197,6 +296,379 @@ pub fn default(event: &Event, jnv: &mut crate::jnv::Jnv) -> anyhow::Result<Promp
             jnv.json.stream.collapse_all();
         }
 
+        // Show where the filter points in the input data
+        Event::Key(KeyEvent {
+            code: KeyCode::Char('c'),
+            modifiers: KeyModifiers::ALT,
+            kind: KeyEventKind::Press,
+            state: KeyEventState::NONE,
+        }) => {
+            jnv.show_filter_context();
+        }
+
+        // Hide the filter editor
+        Event::Key(KeyEvent {
+            code: KeyCode::Char('e'),
+            modifiers: KeyModifiers::ALT,
+            kind: KeyEventKind::Press,
+            state: KeyEventState::NONE,
+        }) => {
+            jnv.editor_visible = false;
+            jnv.keymap.borrow_mut().switch("on_editor_hidden");
+        }
+
+        // Reload the input file
+        Event::Key(KeyEvent {
+            code: KeyCode::Char('r'),
+            modifiers: KeyModifiers::CONTROL,
+            kind: KeyEventKind::Press,
+            state: KeyEventState::NONE,
+        }) => {
+            jnv.reload_input();
+        }
+
+        // Toggle read-only mode of the filter editor
+        Event::Key(KeyEvent {
+            code: KeyCode::Char('r'),
+            modifiers: KeyModifiers::ALT,
+            kind: KeyEventKind::Press,
+            state: KeyEventState::NONE,
+        }) => {
+            jnv.toggle_read_only();
+        }
+
+        // Show the diff from the previous result
+        Event::Key(KeyEvent {
+            code: KeyCode::Char('v'),
+            modifiers: KeyModifiers::ALT,
+            kind: KeyEventKind::Press,
+            state: KeyEventState::NONE,
+        }) => {
+            jnv.toggle_diff();
+        }
+
+        // Show the results of the next or previous matching input only
+        Event::Key(KeyEvent {
+            code: KeyCode::Down,
+            modifiers: KeyModifiers::ALT,
+            kind: KeyEventKind::Press,
+            state: KeyEventState::NONE,
+        }) => {
+            jnv.cycle_matches(true);
+        }
+        Event::Key(KeyEvent {
+            code: KeyCode::Up,
+            modifiers: KeyModifiers::ALT,
+            kind: KeyEventKind::Press,
+            state: KeyEventState::NONE,
+        }) => {
+            jnv.cycle_matches(false);
+        }
+
+        // Switch between absolute and relative line numbers
+        Event::Key(KeyEvent {
+            code: KeyCode::Char('n'),
+            modifiers: KeyModifiers::ALT,
+            kind: KeyEventKind::Press,
+            state: KeyEventState::NONE,
+        }) => {
+            jnv.toggle_relative_line_numbers();
+        }
+
+        // Open the URL under the cursor
+        Event::Key(KeyEvent {
+            code: KeyCode::Char('w'),
+            modifiers: KeyModifiers::ALT,
+            kind: KeyEventKind::Press,
+            state: KeyEventState::NONE,
+        }) => {
+            jnv.open_url();
+        }
+
+        // Copy the screen as plain text
+        Event::Key(KeyEvent {
+            code: KeyCode::Char('s'),
+            modifiers: KeyModifiers::ALT,
+            kind: KeyEventKind::Press,
+            state: KeyEventState::NONE,
+        }) => {
+            jnv.copy_screenshot();
+        }
+
+        // Switch to the next palette
+        Event::Key(KeyEvent {
+            code: KeyCode::Char('t'),
+            modifiers: KeyModifiers::ALT,
+            kind: KeyEventKind::Press,
+            state: KeyEventState::NONE,
+        }) => {
+            jnv.cycle_palette();
+        }
+
+        // Copy a jq command reproducing the current view
+        Event::Key(KeyEvent {
+            code: KeyCode::Char('j'),
+            modifiers: KeyModifiers::ALT,
+            kind: KeyEventKind::Press,
+            state: KeyEventState::NONE,
+        }) => {
+            jnv.copy_jq_command();
+        }
+
+        // Copy the result as a data URI
+        Event::Key(KeyEvent {
+            code: KeyCode::Char('u'),
+            modifiers: KeyModifiers::ALT,
+            kind: KeyEventKind::Press,
+            state: KeyEventState::NONE,
+        }) => {
+            jnv.copy_data_uri();
+        }
+
+        // Copy the result to the clipboard
+        Event::Key(KeyEvent {
+            code: KeyCode::Char('y'),
+            modifiers: KeyModifiers::ALT,
+            kind: KeyEventKind::Press,
+            state: KeyEventState::NONE,
+        }) => {
+            jnv.copy_result();
+        }
+
+        // Copy the filter and the result together as a JSON object
+        Event::Key(KeyEvent {
+            code: KeyCode::Char('q'),
+            modifiers: KeyModifiers::ALT,
+            kind: KeyEventKind::Press,
+            state: KeyEventState::NONE,
+        }) => {
+            jnv.copy_query_and_result();
+        }
+
+        // Switch between copying compact and indented JSON
+        Event::Key(KeyEvent {
+            code: KeyCode::Char('m'),
+            modifiers: KeyModifiers::ALT,
+            kind: KeyEventKind::Press,
+            state: KeyEventState::NONE,
+        }) => {
+            jnv.toggle_compact_copy();
+        }
+
+        // Insert the path of the node under the cursor into the filter
+        Event::Key(KeyEvent {
+            code: KeyCode::Char('i'),
+            modifiers: KeyModifiers::ALT,
+            kind: KeyEventKind::Press,
+            state: KeyEventState::NONE,
+        }) => {
+            jnv.insert_path();
+        }
+
+        // Copy the path of the node under the cursor to the clipboard
+        Event::Key(KeyEvent {
+            code: KeyCode::Char('p'),
+            modifiers: KeyModifiers::ALT,
+            kind: KeyEventKind::Press,
+            state: KeyEventState::NONE,
+        }) => {
+            jnv.copy_path();
+        }
+
+        // Show or hide the line numbers
+        Event::Key(KeyEvent {
+            code: KeyCode::Char('l'),
+            modifiers: KeyModifiers::ALT,
+            kind: KeyEventKind::Press,
+            state: KeyEventState::NONE,
+        }) => {
+            jnv.toggle_line_numbers();
+        }
+
+        // Switch between raw and JSON output of strings
+        Event::Key(KeyEvent {
+            code: KeyCode::Char('o'),
+            modifiers: KeyModifiers::ALT,
+            kind: KeyEventKind::Press,
+            state: KeyEventState::NONE,
+        }) => {
+            jnv.toggle_raw_output();
+        }
+
+        // Wait longer/shorter for typing to pause before running the filter
+        Event::Key(KeyEvent {
+            code: KeyCode::Char('=' | '+'),
+            modifiers: KeyModifiers::ALT,
+            kind: KeyEventKind::Press,
+            state: KeyEventState::NONE,
+        }) => {
+            jnv.adjust_debounce(DEBOUNCE_STEP_MILLIS);
+        }
+        Event::Key(KeyEvent {
+            code: KeyCode::Char('-'),
+            modifiers: KeyModifiers::ALT,
+            kind: KeyEventKind::Press,
+            state: KeyEventState::NONE,
+        }) => {
+            jnv.adjust_debounce(-DEBOUNCE_STEP_MILLIS);
+        }
+
+        // Switch between indentation and tree connectors
+        Event::Key(KeyEvent {
+            code: KeyCode::Char('x'),
+            modifiers: KeyModifiers::ALT,
+            kind: KeyEventKind::Press,
+            state: KeyEventState::NONE,
+        }) => {
+            jnv.toggle_tree_guides();
+        }
+
+        // Switch between showing the values and only their types
+        Event::Key(KeyEvent {
+            code: KeyCode::Char('g'),
+            modifiers: KeyModifiers::ALT,
+            kind: KeyEventKind::Press,
+            state: KeyEventState::NONE,
+        }) => {
+            jnv.toggle_keys_only();
+        }
+
+        // Switch how long values are wrapped
+        Event::Key(KeyEvent {
+            code: KeyCode::Char('z'),
+            modifiers: KeyModifiers::ALT,
+            kind: KeyEventKind::Press,
+            state: KeyEventState::NONE,
+        }) => {
+            jnv.toggle_wrap_values();
+        }
+
+        // Open an input for a path to move the cursor to
+        Event::Key(KeyEvent {
+            code: KeyCode::Char('g'),
+            modifiers: KeyModifiers::CONTROL,
+            kind: KeyEventKind::Press,
+            state: KeyEventState::NONE,
+        }) => {
+            jnv.open_line_input(LineInput::Goto);
+        }
+
+        // Open an input for text to search for in the results
+        Event::Key(KeyEvent {
+            code: KeyCode::Char('f'),
+            modifiers: KeyModifiers::CONTROL,
+            kind: KeyEventKind::Press,
+            state: KeyEventState::NONE,
+        }) => {
+            jnv.open_line_input(LineInput::Find);
+        }
+
+        // Move to the next/previous match of the search
+        Event::Key(KeyEvent {
+            code: KeyCode::F(3),
+            modifiers: KeyModifiers::NONE,
+            kind: KeyEventKind::Press,
+            state: KeyEventState::NONE,
+        }) => {
+            jnv.find_next(true);
+        }
+        Event::Key(KeyEvent {
+            code: KeyCode::F(3),
+            modifiers: KeyModifiers::SHIFT,
+            kind: KeyEventKind::Press,
+            state: KeyEventState::NONE,
+        }) => {
+            jnv.find_next(false);
+        }
+
+        // Open an input for a file to save the result to
+        Event::Key(KeyEvent {
+            code: KeyCode::Char('s'),
+            modifiers: KeyModifiers::CONTROL,
+            kind: KeyEventKind::Press,
+            state: KeyEventState::NONE,
+        }) => {
+            jnv.open_line_input(LineInput::Save);
+        }
+
+        // Show the next/previous page of the result
+        Event::Key(KeyEvent {
+            code: KeyCode::PageDown,
+            modifiers: KeyModifiers::NONE,
+            kind: KeyEventKind::Press,
+            state: KeyEventState::NONE,
+        }) => {
+            jnv.turn_result_page(true);
+        }
+        Event::Key(KeyEvent {
+            code: KeyCode::PageUp,
+            modifiers: KeyModifiers::NONE,
+            kind: KeyEventKind::Press,
+            state: KeyEventState::NONE,
+        }) => {
+            jnv.turn_result_page(false);
+        }
+
+        // Pin or unpin the filter
+        Event::Key(KeyEvent {
+            code: KeyCode::Char('k'),
+            modifiers: KeyModifiers::ALT,
+            kind: KeyEventKind::Press,
+            state: KeyEventState::NONE,
+        }) => {
+            jnv.toggle_pin();
+        }
+
+        // Replace the filter with the previous/next pinned one
+        Event::Key(KeyEvent {
+            code: KeyCode::Char(','),
+            modifiers: KeyModifiers::ALT,
+            kind: KeyEventKind::Press,
+            state: KeyEventState::NONE,
+        }) => {
+            jnv.cycle_pinned(false);
+        }
+        Event::Key(KeyEvent {
+            code: KeyCode::Char('.'),
+            modifiers: KeyModifiers::ALT,
+            kind: KeyEventKind::Press,
+            state: KeyEventState::NONE,
+        }) => {
+            jnv.cycle_pinned(true);
+        }
+
+        // Wrap the filter in a template
+        Event::Key(KeyEvent {
+            code: KeyCode::Char(ch @ '1'..='9'),
+            modifiers: KeyModifiers::ALT,
+            kind: KeyEventKind::Press,
+            state: KeyEventState::NONE,
+        }) => {
+            jnv.wrap_filter(*ch as usize - '1' as usize);
+        }
+
+        // Clear the filter, or reset it to the identity filter
+        Event::Key(KeyEvent {
+            code: KeyCode::Char('x'),
+            modifiers: KeyModifiers::CONTROL,
+            kind: KeyEventKind::Press,
+            state: KeyEventState::NONE,
+        }) => jnv.reset_filter(""),
+        Event::Key(KeyEvent {
+            code: KeyCode::Char('h'),
+            modifiers: KeyModifiers::ALT,
+            kind: KeyEventKind::Press,
+            state: KeyEventState::NONE,
+        }) => jnv.reset_filter("."),
+
+        // Start a new line in the filter
+        Event::Key(KeyEvent {
+            code: KeyCode::Enter,
+            modifiers: KeyModifiers::ALT,
+            kind: KeyEventKind::Press,
+            state: KeyEventState::NONE,
+        }) => filter_editor.texteditor.insert('\n'),
+
         // Input char.
         Event::Key(KeyEvent {
             code: KeyCode::Char(ch),
@@ -219,6 +691,145 @@ pub fn default(event: &Event, jnv: &mut crate::jnv::Jnv) -> anyhow::Result<Promp
     Ok(PromptSignal::Continue)
 }
 
+/// Insert mode of the `vi` preset: the default keys, with Esc switching to normal mode.
+pub fn vi_insert(event: &Event, jnv: &mut crate::jnv::Jnv) -> anyhow::Result<PromptSignal> {
+    match event {
+        Event::Key(KeyEvent {
+            code: KeyCode::Esc,
+            modifiers: KeyModifiers::NONE,
+            kind: KeyEventKind::Press,
+            state: KeyEventState::NONE,
+        }) => jnv.switch_vi_mode(true),
+
+        _ => return default(event, jnv),
+    }
+    Ok(PromptSignal::Continue)
+}
+
+/// Normal mode of the `vi` preset, where letters move the cursors instead of being typed.
+/// Keys with modifiers and special keys work as in the default keymap.
+pub fn vi_normal(event: &Event, jnv: &mut crate::jnv::Jnv) -> anyhow::Result<PromptSignal> {
+    let read_only = jnv.read_only();
+    // The first key of a two-key sequence such as `gg`, if pressed within the timeout.
+    let prefix = jnv
+        .pending_key
+        .take()
+        .filter(|(_, pressed)| pressed.elapsed() < jnv.chord_timeout)
+        .map(|(ch, _)| ch);
+    let filter_editor = jnv.filter_editor.after_mut();
+
+    match event {
+        Event::Key(KeyEvent {
+            code: KeyCode::Esc,
+            modifiers: KeyModifiers::NONE,
+            kind: KeyEventKind::Press,
+            state: KeyEventState::NONE,
+        }) => {}
+
+        Event::Key(KeyEvent {
+            code: KeyCode::Backspace,
+            modifiers: KeyModifiers::NONE,
+            kind: KeyEventKind::Press,
+            state: KeyEventState::NONE,
+        }) => {
+            filter_editor.texteditor.backward();
+        }
+
+        // Redo the last undone edit to the filter
+        Event::Key(KeyEvent {
+            code: KeyCode::Char('r'),
+            modifiers: KeyModifiers::CONTROL,
+            kind: KeyEventKind::Press,
+            state: KeyEventState::NONE,
+        }) if !read_only => jnv.redo(),
+
+        Event::Key(KeyEvent {
+            code: KeyCode::Char(ch),
+            modifiers: KeyModifiers::NONE,
+            kind: KeyEventKind::Press,
+            state: KeyEventState::NONE,
+        })
+        | Event::Key(KeyEvent {
+            code: KeyCode::Char(ch),
+            modifiers: KeyModifiers::SHIFT,
+            kind: KeyEventKind::Press,
+            state: KeyEventState::NONE,
+        }) => match ch {
+            // Back to insert mode, before or after the cursor or at either end of the filter
+            'i' if !read_only => jnv.switch_vi_mode(false),
+            'a' if !read_only => {
+                filter_editor.texteditor.forward();
+                jnv.switch_vi_mode(false);
+            }
+            'I' if !read_only => {
+                filter_editor.texteditor.move_to_head();
+                jnv.switch_vi_mode(false);
+            }
+            'A' if !read_only => {
+                filter_editor.texteditor.move_to_tail();
+                jnv.switch_vi_mode(false);
+            }
+
+            // Move the cursor of the filter editor
+            'h' => {
+                filter_editor.texteditor.backward();
+            }
+            'l' => {
+                filter_editor.texteditor.forward();
+            }
+            '0' | '^' => filter_editor.texteditor.move_to_head(),
+            '$' => filter_editor.texteditor.move_to_tail(),
+            'w' | 'e' => filter_editor
+                .texteditor
+                .move_to_next_nearest(&filter_editor.word_break_chars),
+            'b' => filter_editor
+                .texteditor
+                .move_to_previous_nearest(&filter_editor.word_break_chars),
+
+            // Edit the filter
+            // Erase the character under the cursor, which is the one before it once moved past.
+            'x' if !read_only && filter_editor.texteditor.forward() => {
+                filter_editor.texteditor.erase()
+            }
+            'd' if !read_only && prefix == Some('d') => filter_editor.texteditor.erase_all(),
+            'D' if !read_only => {
+                let head = filter_editor
+                    .texteditor
+                    .text_without_cursor()
+                    .to_string()
+                    .chars()
+                    .take(filter_editor.texteditor.position())
+                    .collect::<String>();
+                filter_editor.texteditor.replace(&head);
+            }
+            'u' if !read_only => jnv.undo(),
+
+            // Move the cursor of the JSON viewer
+            'j' => {
+                jnv.json.stream.forward();
+            }
+            'k' => {
+                jnv.json.stream.backward();
+            }
+            'g' if prefix == Some('g') => jnv.json.stream.move_to_head(),
+            'G' => jnv.json.stream.move_to_tail(),
+
+            // Search the results
+            '/' => jnv.open_line_input(LineInput::Find),
+            'n' => jnv.find_next(true),
+            'N' => jnv.find_next(false),
+
+            // Wait for the second key of `gg` or `dd`
+            'g' | 'd' => jnv.pending_key = Some((*ch, Instant::now())),
+
+            _ => {}
+        },
+
+        _ => return default(event, jnv),
+    }
+    Ok(PromptSignal::Continue)
+}
+
 pub fn on_suggest(event: &Event, jnv: &mut crate::jnv::Jnv) -> anyhow::Result<PromptSignal> {
     let query_editor_after_mut = jnv.filter_editor.after_mut();
 
@@ -261,6 +872,22 @@ pub fn on_suggest(event: &Event, jnv: &mut crate::jnv::Jnv) -> anyhow::Result<Pr
         }
 
         _ => {
+            // Enter picks the candidate chosen from several, and any other key
+            // leaves it in the filter without counting it as picked.
+            let picked = matches!(
+                event,
+                Event::Key(KeyEvent {
+                    code: KeyCode::Enter,
+                    modifiers: KeyModifiers::NONE,
+                    kind: KeyEventKind::Press,
+                    state: KeyEventState::NONE,
+                })
+            );
+            if picked && jnv.suggestions.listbox.items().len() > 1 {
+                *jnv.suggestion_uses
+                    .entry(jnv.suggestions.listbox.get().to_string())
+                    .or_default() += 1;
+            }
             jnv.suggestions.listbox = Listbox::from_iter(Vec::<String>::new());
             jnv.keymap.borrow_mut().switch("default");
 
@@ -282,3 +909,100 @@ pub fn on_suggest(event: &Event, jnv: &mut crate::jnv::Jnv) -> anyhow::Result<Pr
     }
     Ok(PromptSignal::Continue)
 }
+
+pub fn on_editor_hidden(event: &Event, jnv: &mut crate::jnv::Jnv) -> anyhow::Result<PromptSignal> {
+    match event {
+        // Show the filter editor again
+        Event::Key(KeyEvent {
+            code: KeyCode::Char('e'),
+            modifiers: KeyModifiers::ALT,
+            kind: KeyEventKind::Press,
+            state: KeyEventState::NONE,
+        }) => {
+            jnv.editor_visible = true;
+            jnv.keymap.borrow_mut().switch("default");
+        }
+
+        // Ignore the keys that would edit the hidden filter.
+        event if edits_filter(event) => {}
+
+        _ => return default(event, jnv),
+    }
+    Ok(PromptSignal::Continue)
+}
+
+pub fn on_line_input(event: &Event, jnv: &mut crate::jnv::Jnv) -> anyhow::Result<PromptSignal> {
+    let Some((_, editor)) = jnv.line_input.as_mut() else {
+        return default(event, jnv);
+    };
+
+    match event {
+        Event::Key(KeyEvent {
+            code: KeyCode::Char('c'),
+            modifiers: KeyModifiers::CONTROL,
+            kind: KeyEventKind::Press,
+            state: KeyEventState::NONE,
+        }) => return Ok(PromptSignal::Quit),
+
+        // Act on the entered text
+        Event::Key(KeyEvent {
+            code: KeyCode::Enter,
+            modifiers: KeyModifiers::NONE,
+            kind: KeyEventKind::Press,
+            state: KeyEventState::NONE,
+        }) => jnv.close_line_input(false),
+
+        // Close without acting
+        Event::Key(KeyEvent {
+            code: KeyCode::Esc,
+            modifiers: KeyModifiers::NONE,
+            kind: KeyEventKind::Press,
+            state: KeyEventState::NONE,
+        })
+        | Event::Key(KeyEvent {
+            code: KeyCode::Char('g' | 's' | 'f'),
+            modifiers: KeyModifiers::CONTROL,
+            kind: KeyEventKind::Press,
+            state: KeyEventState::NONE,
+        }) => jnv.close_line_input(true),
+
+        Event::Key(KeyEvent {
+            code: KeyCode::Left,
+            modifiers: KeyModifiers::NONE,
+            kind: KeyEventKind::Press,
+            state: KeyEventState::NONE,
+        }) => {
+            editor.texteditor.backward();
+        }
+        Event::Key(KeyEvent {
+            code: KeyCode::Right,
+            modifiers: KeyModifiers::NONE,
+            kind: KeyEventKind::Press,
+            state: KeyEventState::NONE,
+        }) => {
+            editor.texteditor.forward();
+        }
+        Event::Key(KeyEvent {
+            code: KeyCode::Backspace,
+            modifiers: KeyModifiers::NONE,
+            kind: KeyEventKind::Press,
+            state: KeyEventState::NONE,
+        }) => editor.texteditor.erase(),
+
+        Event::Key(KeyEvent {
+            code: KeyCode::Char(ch),
+            modifiers: KeyModifiers::NONE,
+            kind: KeyEventKind::Press,
+            state: KeyEventState::NONE,
+        })
+        | Event::Key(KeyEvent {
+            code: KeyCode::Char(ch),
+            modifiers: KeyModifiers::SHIFT,
+            kind: KeyEventKind::Press,
+            state: KeyEventState::NONE,
+        }) => editor.texteditor.insert(*ch),
+
+        _ => (),
+    }
+    Ok(PromptSignal::Continue)
+}
@@ -0,0 +1,444 @@
+use anyhow::{anyhow, Result};
+use promkit::{
+    crossterm::style::{Attribute, Attributes, Color, ContentStyle},
+    style::StyleBuilder,
+    text_editor,
+};
+
+use super::JsonTheme;
+use crate::query::TokenKind;
+
+/// Styles for the parts of the filter highlighted in the filter editor.
+#[derive(Clone, Copy)]
+pub struct QueryStyles {
+    pub pipe: ContentStyle,
+    pub string: ContentStyle,
+    pub field: ContentStyle,
+    pub builtin: ContentStyle,
+}
+
+impl QueryStyles {
+    pub fn style(&self, kind: TokenKind) -> ContentStyle {
+        match kind {
+            TokenKind::Pipe => self.pipe,
+            TokenKind::String => self.string,
+            TokenKind::Field => self.field,
+            TokenKind::Builtin => self.builtin,
+        }
+    }
+}
+
+/// A named set of colors for the filter editor and the JSON viewer.
+pub struct Palette {
+    pub name: &'static str,
+
+    /// Style for the prompt of the filter editor.
+    pub prefix_style: ContentStyle,
+    /// Style for the character under the cursor of the filter editor.
+    pub active_char_style: ContentStyle,
+    /// Style for the bracket matching the one under the cursor of the filter editor.
+    pub bracket_match_style: ContentStyle,
+    /// Styles for the syntax of the filter.
+    pub query_styles: QueryStyles,
+
+    pub curly_brackets_style: ContentStyle,
+    pub square_brackets_style: ContentStyle,
+    pub key_style: ContentStyle,
+    pub string_value_style: ContentStyle,
+    pub number_value_style: ContentStyle,
+    pub boolean_value_style: ContentStyle,
+    pub null_value_style: ContentStyle,
+
+    /// Style for the text matching the search in the results.
+    pub match_style: ContentStyle,
+}
+
+/// Names of the styles whose color can be overridden with `--color`.
+pub const COLOR_FIELDS: [&str; 15] = [
+    "prefix",
+    "active-char",
+    "bracket-match",
+    "query-pipe",
+    "query-string",
+    "query-field",
+    "query-builtin",
+    "curly-brackets",
+    "square-brackets",
+    "key",
+    "string",
+    "number",
+    "boolean",
+    "null",
+    "match",
+];
+
+/// Parses a color name known to crossterm (e.g. `dark_grey`)
+/// or a 24-bit hex color (e.g. `#ff8800`).
+pub fn parse_color(val: &str) -> Result<Color> {
+    let invalid = || {
+        anyhow!(
+            "invalid color '{}': expected a name such as 'dark_grey' or a hex color such as '#ff8800'",
+            val
+        )
+    };
+    match val.strip_prefix('#') {
+        Some(hex) if hex.len() == 6 && hex.chars().all(|c| c.is_ascii_hexdigit()) => {
+            let channel = |i: usize| u8::from_str_radix(&hex[i..i + 2], 16).map_err(|_| invalid());
+            Ok(Color::Rgb {
+                r: channel(0)?,
+                g: channel(2)?,
+                b: channel(4)?,
+            })
+        }
+        Some(_) => Err(invalid()),
+        None => Color::try_from(val).map_err(|_| invalid()),
+    }
+}
+
+/// Parses `FIELD=COLOR`, where `FIELD` is one of [`COLOR_FIELDS`].
+pub fn parse_color_override(val: &str) -> Result<(String, Color)> {
+    let (field, color) = val
+        .split_once('=')
+        .ok_or_else(|| anyhow!("expected FIELD=COLOR, e.g. 'key=#ff8800'"))?;
+    if !COLOR_FIELDS.contains(&field) {
+        return Err(anyhow!(
+            "unknown field '{}': expected one of {}",
+            field,
+            COLOR_FIELDS.join(", ")
+        ));
+    }
+    Ok((field.to_string(), parse_color(color)?))
+}
+
+impl Palette {
+    /// Replaces the foreground colors of the given styles
+    /// (the background color for `active-char`, `bracket-match` and `match`).
+    pub fn with_colors(mut self, colors: &[(String, Color)]) -> Self {
+        for (field, color) in colors {
+            let style = match field.as_str() {
+                "prefix" => &mut self.prefix_style,
+                "active-char" => {
+                    self.active_char_style.background_color = Some(*color);
+                    continue;
+                }
+                "bracket-match" => {
+                    self.bracket_match_style.background_color = Some(*color);
+                    continue;
+                }
+                "match" => {
+                    self.match_style.background_color = Some(*color);
+                    continue;
+                }
+                "query-pipe" => &mut self.query_styles.pipe,
+                "query-string" => &mut self.query_styles.string,
+                "query-field" => &mut self.query_styles.field,
+                "query-builtin" => &mut self.query_styles.builtin,
+                "curly-brackets" => &mut self.curly_brackets_style,
+                "square-brackets" => &mut self.square_brackets_style,
+                "key" => &mut self.key_style,
+                "string" => &mut self.string_value_style,
+                "number" => &mut self.number_value_style,
+                "boolean" => &mut self.boolean_value_style,
+                "null" => &mut self.null_value_style,
+                _ => continue,
+            };
+            style.foreground_color = Some(*color);
+        }
+        self
+    }
+
+    /// Applies the colors to the filter editor and the JSON viewer.
+    pub fn apply(&self, editor: &mut text_editor::State, theme: &mut JsonTheme) {
+        editor.prefix_style = self.prefix_style;
+        editor.active_char_style = self.active_char_style;
+
+        theme.curly_brackets_style = self.curly_brackets_style;
+        theme.square_brackets_style = self.square_brackets_style;
+        theme.key_style = self.key_style;
+        theme.string_value_style = self.string_value_style;
+        theme.number_value_style = self.number_value_style;
+        theme.boolean_value_style = self.boolean_value_style;
+        theme.null_value_style = self.null_value_style;
+        theme.match_style = self.match_style;
+    }
+}
+
+/// Returns the built-in palettes, starting with the default one.
+pub fn palettes() -> Vec<Palette> {
+    let bold = StyleBuilder::new()
+        .attrs(Attributes::from(Attribute::Bold))
+        .build();
+    // Presets ported from popular editor themes, for terminals supporting truecolor.
+    let fg = |r, g, b| StyleBuilder::new().fgc(Color::Rgb { r, g, b }).build();
+    let bg = |r, g, b| StyleBuilder::new().bgc(Color::Rgb { r, g, b }).build();
+    vec![
+        Palette {
+            name: "default",
+            prefix_style: StyleBuilder::new().fgc(Color::Blue).build(),
+            active_char_style: StyleBuilder::new().bgc(Color::Magenta).build(),
+            bracket_match_style: StyleBuilder::new()
+                .fgc(Color::Black)
+                .bgc(Color::Cyan)
+                .build(),
+            query_styles: QueryStyles {
+                pipe: StyleBuilder::new().fgc(Color::Blue).build(),
+                string: StyleBuilder::new().fgc(Color::Green).build(),
+                field: StyleBuilder::new().fgc(Color::Cyan).build(),
+                builtin: StyleBuilder::new().fgc(Color::Yellow).build(),
+            },
+            curly_brackets_style: bold,
+            square_brackets_style: bold,
+            key_style: StyleBuilder::new().fgc(Color::Cyan).build(),
+            string_value_style: StyleBuilder::new().fgc(Color::Green).build(),
+            number_value_style: StyleBuilder::new().build(),
+            boolean_value_style: StyleBuilder::new().build(),
+            null_value_style: StyleBuilder::new().fgc(Color::Grey).build(),
+            match_style: StyleBuilder::new()
+                .fgc(Color::Black)
+                .bgc(Color::Yellow)
+                .build(),
+        },
+        // Darker colors that stay readable on light backgrounds.
+        Palette {
+            name: "light",
+            prefix_style: StyleBuilder::new().fgc(Color::DarkBlue).build(),
+            active_char_style: StyleBuilder::new()
+                .fgc(Color::White)
+                .bgc(Color::DarkMagenta)
+                .build(),
+            bracket_match_style: StyleBuilder::new()
+                .fgc(Color::White)
+                .bgc(Color::DarkCyan)
+                .build(),
+            query_styles: QueryStyles {
+                pipe: StyleBuilder::new().fgc(Color::DarkBlue).build(),
+                string: StyleBuilder::new().fgc(Color::DarkGreen).build(),
+                field: StyleBuilder::new().fgc(Color::DarkCyan).build(),
+                builtin: StyleBuilder::new().fgc(Color::DarkYellow).build(),
+            },
+            curly_brackets_style: bold,
+            square_brackets_style: bold,
+            key_style: StyleBuilder::new().fgc(Color::DarkBlue).build(),
+            string_value_style: StyleBuilder::new().fgc(Color::DarkGreen).build(),
+            number_value_style: StyleBuilder::new().fgc(Color::DarkMagenta).build(),
+            boolean_value_style: StyleBuilder::new().fgc(Color::DarkYellow).build(),
+            null_value_style: StyleBuilder::new().fgc(Color::DarkGrey).build(),
+            match_style: StyleBuilder::new()
+                .fgc(Color::Black)
+                .bgc(Color::Yellow)
+                .build(),
+        },
+        // No colors at all, only text attributes.
+        Palette {
+            name: "mono",
+            prefix_style: bold,
+            active_char_style: StyleBuilder::new()
+                .attrs(Attributes::from(Attribute::Reverse))
+                .build(),
+            bracket_match_style: StyleBuilder::new()
+                .attrs(Attributes::from(Attribute::Bold) | Attribute::Underlined)
+                .build(),
+            query_styles: QueryStyles {
+                pipe: bold,
+                string: StyleBuilder::new().build(),
+                field: StyleBuilder::new().build(),
+                builtin: StyleBuilder::new()
+                    .attrs(Attributes::from(Attribute::Italic))
+                    .build(),
+            },
+            curly_brackets_style: bold,
+            square_brackets_style: bold,
+            key_style: bold,
+            string_value_style: StyleBuilder::new().build(),
+            number_value_style: StyleBuilder::new().build(),
+            boolean_value_style: StyleBuilder::new()
+                .attrs(Attributes::from(Attribute::Italic))
+                .build(),
+            null_value_style: StyleBuilder::new()
+                .attrs(Attributes::from(Attribute::Italic))
+                .build(),
+            match_style: StyleBuilder::new()
+                .attrs(Attributes::from(Attribute::Underlined))
+                .build(),
+        },
+        Palette {
+            name: "monokai",
+            prefix_style: fg(0xa6, 0xe2, 0x2e),
+            active_char_style: bg(0x75, 0x71, 0x5e),
+            bracket_match_style: bg(0x49, 0x48, 0x3e),
+            query_styles: QueryStyles {
+                pipe: fg(0xf9, 0x26, 0x72),
+                string: fg(0xe6, 0xdb, 0x74),
+                field: fg(0xa6, 0xe2, 0x2e),
+                builtin: fg(0x66, 0xd9, 0xef),
+            },
+            curly_brackets_style: fg(0xf8, 0xf8, 0xf2),
+            square_brackets_style: fg(0xf8, 0xf8, 0xf2),
+            key_style: fg(0xf9, 0x26, 0x72),
+            string_value_style: fg(0xe6, 0xdb, 0x74),
+            number_value_style: fg(0xae, 0x81, 0xff),
+            boolean_value_style: fg(0x66, 0xd9, 0xef),
+            null_value_style: fg(0x75, 0x71, 0x5e),
+            match_style: StyleBuilder::new()
+                .fgc(Color::Rgb {
+                    r: 0x27,
+                    g: 0x28,
+                    b: 0x22,
+                })
+                .bgc(Color::Rgb {
+                    r: 0xe6,
+                    g: 0xdb,
+                    b: 0x74,
+                })
+                .build(),
+        },
+        Palette {
+            name: "solarized-dark",
+            prefix_style: fg(0x26, 0x8b, 0xd2),
+            active_char_style: bg(0x58, 0x6e, 0x75),
+            bracket_match_style: bg(0x07, 0x36, 0x42),
+            query_styles: QueryStyles {
+                pipe: fg(0x85, 0x99, 0x00),
+                string: fg(0x2a, 0xa1, 0x98),
+                field: fg(0x26, 0x8b, 0xd2),
+                builtin: fg(0xb5, 0x89, 0x00),
+            },
+            curly_brackets_style: fg(0x93, 0xa1, 0xa1),
+            square_brackets_style: fg(0x93, 0xa1, 0xa1),
+            key_style: fg(0x26, 0x8b, 0xd2),
+            string_value_style: fg(0x2a, 0xa1, 0x98),
+            number_value_style: fg(0xd3, 0x36, 0x82),
+            boolean_value_style: fg(0xb5, 0x89, 0x00),
+            null_value_style: fg(0x58, 0x6e, 0x75),
+            match_style: StyleBuilder::new()
+                .fgc(Color::Rgb {
+                    r: 0x00,
+                    g: 0x2b,
+                    b: 0x36,
+                })
+                .bgc(Color::Rgb {
+                    r: 0xb5,
+                    g: 0x89,
+                    b: 0x00,
+                })
+                .build(),
+        },
+        Palette {
+            name: "solarized-light",
+            prefix_style: fg(0x26, 0x8b, 0xd2),
+            active_char_style: bg(0x93, 0xa1, 0xa1),
+            bracket_match_style: bg(0xee, 0xe8, 0xd5),
+            query_styles: QueryStyles {
+                pipe: fg(0x85, 0x99, 0x00),
+                string: fg(0x2a, 0xa1, 0x98),
+                field: fg(0x26, 0x8b, 0xd2),
+                builtin: fg(0xb5, 0x89, 0x00),
+            },
+            curly_brackets_style: fg(0x58, 0x6e, 0x75),
+            square_brackets_style: fg(0x58, 0x6e, 0x75),
+            key_style: fg(0x26, 0x8b, 0xd2),
+            string_value_style: fg(0x2a, 0xa1, 0x98),
+            number_value_style: fg(0xd3, 0x36, 0x82),
+            boolean_value_style: fg(0xb5, 0x89, 0x00),
+            null_value_style: fg(0x93, 0xa1, 0xa1),
+            match_style: StyleBuilder::new()
+                .fgc(Color::Rgb {
+                    r: 0xfd,
+                    g: 0xf6,
+                    b: 0xe3,
+                })
+                .bgc(Color::Rgb {
+                    r: 0xb5,
+                    g: 0x89,
+                    b: 0x00,
+                })
+                .build(),
+        },
+        Palette {
+            name: "nord",
+            prefix_style: fg(0x88, 0xc0, 0xd0),
+            active_char_style: bg(0x5e, 0x81, 0xac),
+            bracket_match_style: bg(0x43, 0x4c, 0x5e),
+            query_styles: QueryStyles {
+                pipe: fg(0x81, 0xa1, 0xc1),
+                string: fg(0xa3, 0xbe, 0x8c),
+                field: fg(0x88, 0xc0, 0xd0),
+                builtin: fg(0x8f, 0xbc, 0xbb),
+            },
+            curly_brackets_style: fg(0xec, 0xef, 0xf4),
+            square_brackets_style: fg(0xec, 0xef, 0xf4),
+            key_style: fg(0x88, 0xc0, 0xd0),
+            string_value_style: fg(0xa3, 0xbe, 0x8c),
+            number_value_style: fg(0xb4, 0x8e, 0xad),
+            boolean_value_style: fg(0x81, 0xa1, 0xc1),
+            null_value_style: fg(0x4c, 0x56, 0x6a),
+            match_style: StyleBuilder::new()
+                .fgc(Color::Rgb {
+                    r: 0x2e,
+                    g: 0x34,
+                    b: 0x40,
+                })
+                .bgc(Color::Rgb {
+                    r: 0xeb,
+                    g: 0xcb,
+                    b: 0x8b,
+                })
+                .build(),
+        },
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    mod parse_color {
+        use super::*;
+
+        #[test]
+        fn test_hex() {
+            assert_eq!(
+                Color::Rgb {
+                    r: 0xff,
+                    g: 0x88,
+                    b: 0x00
+                },
+                parse_color("#ff8800").unwrap()
+            );
+            assert_eq!(
+                Color::Rgb {
+                    r: 10,
+                    g: 11,
+                    b: 12
+                },
+                parse_color("#0A0b0C").unwrap()
+            );
+        }
+
+        #[test]
+        fn test_name() {
+            assert_eq!(Color::DarkGrey, parse_color("dark_grey").unwrap());
+        }
+
+        #[test]
+        fn test_invalid() {
+            assert!(parse_color("#ff88").is_err());
+            assert!(parse_color("#gg8800").is_err());
+            assert!(parse_color("orange").is_err());
+        }
+    }
+
+    mod parse_color_override {
+        use super::*;
+
+        #[test]
+        fn test() {
+            assert_eq!(
+                ("key".to_string(), Color::Rgb { r: 255, g: 0, b: 0 }),
+                parse_color_override("key=#ff0000").unwrap()
+            );
+            assert!(parse_color_override("keys=red").is_err());
+            assert!(parse_color_override("red").is_err());
+        }
+    }
+}
@@ -0,0 +1,55 @@
+use promkit::pane::Pane;
+
+/// Lays out `panes` in a terminal of `height` rows the same way
+/// the prompt draws them, returning the plain text of each row.
+pub fn compose(panes: &[Pane], height: usize) -> Vec<String> {
+    let viewable_panes = panes
+        .iter()
+        .filter(|pane| !pane.is_empty())
+        .collect::<Vec<_>>();
+
+    let mut rows = Vec::new();
+    for (i, pane) in viewable_panes.iter().enumerate() {
+        // Leave at least one row for each of the remaining panes.
+        let available = height.saturating_sub(rows.len() + viewable_panes.len() - 1 - i);
+        rows.extend(
+            pane.extract(available.max(1))
+                .iter()
+                .map(|row| row.to_string()),
+        );
+    }
+    rows
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    mod compose {
+        use super::*;
+        use promkit::grapheme::StyledGraphemes;
+
+        fn pane(rows: &[&str]) -> Pane {
+            Pane::new(rows.iter().map(StyledGraphemes::from).collect(), 0)
+        }
+
+        #[test]
+        fn test_fits() {
+            assert_eq!(
+                vec!["a", "b", "c"],
+                compose(&[pane(&["a"]), pane(&[]), pane(&["b", "c"])], 5)
+            );
+        }
+
+        #[test]
+        fn test_truncated() {
+            assert_eq!(
+                vec!["a", "b", "c", "f"],
+                compose(
+                    &[pane(&["a"]), pane(&["b", "c", "d", "e"]), pane(&["f"])],
+                    4
+                )
+            );
+        }
+    }
+}
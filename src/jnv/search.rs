@@ -0,0 +1,177 @@
+use std::collections::HashMap;
+
+use super::{SearchMode, SuggestionSort};
+
+/// Returns the `candidates` matching `query` according to `mode`, best matches first.
+/// Ties are broken by shorter candidates, then alphabetically.
+pub fn search(candidates: &[String], query: &str, mode: SearchMode) -> Vec<String> {
+    let mut ret = candidates
+        .iter()
+        .filter_map(|candidate| {
+            let score = match mode {
+                SearchMode::Prefix => candidate.starts_with(query).then_some(0),
+                // Matches further to the left rank higher.
+                SearchMode::Substring => candidate.find(query).map(|i| -(i as i64)),
+                SearchMode::Fuzzy => fuzzy_score(candidate, query),
+            }?;
+            Some((score, candidate))
+        })
+        .collect::<Vec<_>>();
+    ret.sort_by(|(a_score, a), (b_score, b)| {
+        b_score
+            .cmp(a_score)
+            .then_with(|| a.len().cmp(&b.len()))
+            .then_with(|| a.cmp(b))
+    });
+    ret.into_iter()
+        .map(|(_, candidate)| candidate.clone())
+        .collect()
+}
+
+/// Reorders the `candidates` returned by [`search`] according to `order`,
+/// where `uses` counts how many times each candidate was picked from the suggestions.
+/// Candidates that compare equal keep their order, i.e. the better match comes first.
+pub fn sort(candidates: &mut [String], order: SuggestionSort, uses: &HashMap<String, usize>) {
+    match order {
+        SuggestionSort::Relevance => {}
+        SuggestionSort::Alphabetical => candidates.sort(),
+        SuggestionSort::Depth => candidates.sort_by_key(|candidate| depth(candidate)),
+        SuggestionSort::Frequency => candidates.sort_by_key(|candidate| {
+            std::cmp::Reverse(uses.get(candidate).copied().unwrap_or_default())
+        }),
+    }
+}
+
+/// Returns the number of segments of the path `candidate`, e.g. 3 for `.items[0].name`.
+fn depth(candidate: &str) -> usize {
+    crate::path::parse_query(candidate).map_or(usize::MAX, |path| path.len())
+}
+
+/// Scores how well `query` matches `candidate` when its characters appear
+/// in order but not necessarily next to each other (case-insensitively),
+/// e.g. `usrnm` matches `.user.name`. Returns `None` if they do not all appear.
+/// Consecutive characters and characters starting a path segment score higher.
+pub fn fuzzy_score(candidate: &str, query: &str) -> Option<i64> {
+    let candidate = candidate.chars().collect::<Vec<_>>();
+    let mut score = 0;
+    let mut next = 0;
+    let mut previous = None;
+    for q in query.chars() {
+        let i =
+            (next..candidate.len()).find(|&i| candidate[i].to_lowercase().eq(q.to_lowercase()))?;
+        score += 1;
+        if previous.is_some_and(|previous| previous + 1 == i) {
+            score += 5;
+        }
+        if i == 0 || matches!(candidate[i - 1], '.' | '[' | '"' | '_' | '-') {
+            score += 3;
+        }
+        // Penalize skipping characters, but less than a match is worth.
+        score -= (i - next).min(3) as i64;
+        previous = Some(i);
+        next = i + 1;
+    }
+    Some(score)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn candidates() -> Vec<String> {
+        [
+            ".user",
+            ".user.name",
+            ".users",
+            ".username",
+            ".items[0].user",
+        ]
+        .iter()
+        .map(|s| s.to_string())
+        .collect()
+    }
+
+    mod search {
+        use super::*;
+
+        #[test]
+        fn test_prefix() {
+            assert_eq!(
+                vec![".user", ".users", ".username", ".user.name"],
+                search(&candidates(), ".user", SearchMode::Prefix)
+            );
+        }
+
+        #[test]
+        fn test_substring() {
+            // Matches further to the left come first.
+            assert_eq!(
+                vec![".username", ".user.name"],
+                search(&candidates(), "name", SearchMode::Substring)
+            );
+        }
+
+        #[test]
+        fn test_fuzzy() {
+            assert_eq!(
+                vec![".username", ".user.name"],
+                search(&candidates(), "usrnm", SearchMode::Fuzzy)
+            );
+        }
+    }
+
+    mod sort {
+        use super::*;
+
+        fn sorted(order: SuggestionSort, uses: &HashMap<String, usize>) -> Vec<String> {
+            let mut ret = search(&candidates(), ".user", SearchMode::Prefix);
+            sort(&mut ret, order, uses);
+            ret
+        }
+
+        #[test]
+        fn test_alphabetical() {
+            assert_eq!(
+                vec![".user", ".user.name", ".username", ".users"],
+                sorted(SuggestionSort::Alphabetical, &HashMap::new())
+            );
+        }
+
+        #[test]
+        fn test_depth() {
+            assert_eq!(
+                vec![".user", ".users", ".username", ".user.name"],
+                sorted(SuggestionSort::Depth, &HashMap::new())
+            );
+        }
+
+        #[test]
+        fn test_frequency() {
+            let uses = HashMap::from([(".username".to_string(), 2), (".users".to_string(), 1)]);
+            assert_eq!(
+                vec![".username", ".users", ".user", ".user.name"],
+                sorted(SuggestionSort::Frequency, &uses)
+            );
+        }
+    }
+
+    mod fuzzy_score {
+        use super::*;
+
+        #[test]
+        fn test_no_match() {
+            assert_eq!(None, fuzzy_score(".user.name", "nu"));
+            assert_eq!(None, fuzzy_score(".user", "users"));
+        }
+
+        #[test]
+        fn test_ranking() {
+            // Consecutive characters score higher than scattered ones.
+            assert!(fuzzy_score(".name", "nam") > fuzzy_score(".nxaxm", "nam"));
+            // Characters starting a segment score higher.
+            assert!(fuzzy_score(".a.b", "ab") > fuzzy_score(".xaxb", "ab"));
+            // Case is ignored.
+            assert!(fuzzy_score(".UserName", "usrnm").is_some());
+        }
+    }
+}
@@ -0,0 +1,101 @@
+/// Undo and redo history of the edits to the filter,
+/// separate from the history of submitted filters kept by the text editor.
+#[derive(Default)]
+pub struct EditHistory {
+    undo: Vec<String>,
+    redo: Vec<String>,
+    // Whether the last edit typed a character, so that the next one joins its undo unit.
+    inserting: bool,
+    // Whether the last change to the filter was made by undo or redo itself.
+    restored: bool,
+}
+
+impl EditHistory {
+    /// Records that the filter was edited from `before`.
+    /// Consecutively typed characters (`is_insert`) are undone together.
+    pub fn record(&mut self, before: &str, is_insert: bool) {
+        if std::mem::take(&mut self.restored) {
+            return;
+        }
+        if !(is_insert && self.inserting) {
+            self.undo.push(before.to_string());
+        }
+        self.redo.clear();
+        self.inserting = is_insert;
+    }
+
+    /// Ends the current undo unit, e.g. when the cursor moved without editing.
+    pub fn end_unit(&mut self) {
+        self.inserting = false;
+        self.restored = false;
+    }
+
+    /// Returns the filter before the last edit, given the `current` one.
+    pub fn undo(&mut self, current: &str) -> Option<String> {
+        let previous = self.undo.pop()?;
+        self.redo.push(current.to_string());
+        self.inserting = false;
+        self.restored = true;
+        Some(previous)
+    }
+
+    /// Returns the filter of the last undone edit, given the `current` one.
+    pub fn redo(&mut self, current: &str) -> Option<String> {
+        let next = self.redo.pop()?;
+        self.undo.push(current.to_string());
+        self.inserting = false;
+        self.restored = true;
+        Some(next)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    mod edit_history {
+        use super::*;
+
+        fn type_chars(history: &mut EditHistory, filter: &mut String, chars: &str) {
+            for ch in chars.chars() {
+                history.record(filter, true);
+                filter.push(ch);
+            }
+        }
+
+        #[test]
+        fn test_coalesce_inserts() {
+            let mut history = EditHistory::default();
+            let mut filter = String::new();
+            type_chars(&mut history, &mut filter, ".foo");
+            history.end_unit();
+            type_chars(&mut history, &mut filter, ".bar");
+
+            assert_eq!(Some(".foo".to_string()), history.undo(&filter));
+            assert_eq!(Some("".to_string()), history.undo(".foo"));
+            assert_eq!(None, history.undo(""));
+        }
+
+        #[test]
+        fn test_redo() {
+            let mut history = EditHistory::default();
+            history.record("", false);
+            history.record(".a", false);
+
+            assert_eq!(Some(".a".to_string()), history.undo(".a | keys"));
+            history.record(".a | keys", false);
+            assert_eq!(Some(".a | keys".to_string()), history.redo(".a"));
+            assert_eq!(None, history.redo(".a | keys"));
+        }
+
+        #[test]
+        fn test_edit_clears_redo() {
+            let mut history = EditHistory::default();
+            history.record("", false);
+            assert_eq!(Some("".to_string()), history.undo(".a"));
+            history.end_unit();
+            history.record("", true);
+            assert_eq!(None, history.redo(".b"));
+        }
+    }
+}
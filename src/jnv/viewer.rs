@@ -0,0 +1,990 @@
+use std::{
+    cell::RefCell,
+    collections::HashMap,
+    ops::{Deref, Range},
+};
+
+use promkit::{
+    crossterm::style::{Attribute, Color},
+    grapheme::{StyledGrapheme, StyledGraphemes},
+    json::{JsonNode, JsonStream, JsonSyntaxKind},
+    pane::Pane,
+    serde_json,
+    style::StyleBuilder,
+    PaneFactory,
+};
+
+use regex::Regex;
+
+use super::{find, Ellipsis, JsonTheme, LineNumbers};
+
+/// A `JsonStream` that keeps track of the index of the line under its cursor,
+/// counted across all JSON values in the stream, as the cursor moves,
+/// since promkit does not expose it.
+#[derive(Clone)]
+pub struct Stream {
+    inner: JsonStream,
+    position: usize,
+}
+
+impl From<JsonStream> for Stream {
+    fn from(mut inner: JsonStream) -> Self {
+        let position = locate(&mut inner);
+        Self { inner, position }
+    }
+}
+
+impl Deref for Stream {
+    type Target = JsonStream;
+
+    fn deref(&self) -> &JsonStream {
+        &self.inner
+    }
+}
+
+impl Stream {
+    /// Returns the index of the line under the cursor.
+    pub fn position(&self) -> usize {
+        self.position
+    }
+
+    pub fn forward(&mut self) -> bool {
+        let moved = self.inner.forward();
+        if moved {
+            self.position += 1;
+        }
+        moved
+    }
+
+    pub fn backward(&mut self) -> bool {
+        let moved = self.inner.backward();
+        if moved {
+            self.position -= 1;
+        }
+        moved
+    }
+
+    pub fn shift(&mut self, backward: usize, forward: usize) -> bool {
+        let moved = self.inner.shift(backward, forward);
+        if moved {
+            self.position = self.position - backward + forward;
+        }
+        moved
+    }
+
+    pub fn move_to_head(&mut self) {
+        self.inner.move_to_head();
+        self.position = 0;
+    }
+
+    pub fn move_to_tail(&mut self) {
+        self.inner.move_to_tail();
+        self.position = locate(&mut self.inner);
+    }
+
+    /// Folds or unfolds the node under the cursor, which stays on the same line.
+    pub fn toggle(&mut self) {
+        self.inner.toggle();
+    }
+
+    pub fn expand_all(&mut self) {
+        self.inner.expand_all();
+        self.position = locate(&mut self.inner);
+    }
+
+    pub fn collapse_all(&mut self) {
+        self.inner.collapse_all();
+        self.position = locate(&mut self.inner);
+    }
+}
+
+/// Returns the index of the line under the cursor of `stream` without copying it,
+/// by moving the cursor back in steps doubling and then halving in size
+/// (`shift` moves it only if there are enough lines above) and forward again.
+fn locate(stream: &mut JsonStream) -> usize {
+    let mut position = 0;
+    let mut step = 1;
+    while stream.shift(step, 0) {
+        position += step;
+        step *= 2;
+    }
+    while step > 1 {
+        step /= 2;
+        if stream.shift(step, 0) {
+            position += step;
+        }
+    }
+    stream.shift(0, position);
+    position
+}
+
+/// Folds or unfolds the arrays and objects of the first values in `stream`
+/// the way they are shown in `old`, whose values they are,
+/// e.g. after more values were appended to those of `old`.
+/// Nodes hidden within a folded one in `old` keep their state in `stream`.
+pub fn restore_folds(stream: &mut Stream, old: &JsonStream) {
+    // Paths are not hashable, but their debug output tells them apart.
+    fn fold_state(kind: &JsonSyntaxKind) -> Option<(String, bool)> {
+        match kind {
+            JsonSyntaxKind::MapStart { path, .. } | JsonSyntaxKind::ArrayStart { path, .. } => {
+                Some((format!("{:?}", path), false))
+            }
+            JsonSyntaxKind::MapFolded { path, .. } | JsonSyntaxKind::ArrayFolded { path, .. } => {
+                Some((format!("{:?}", path), true))
+            }
+            _ => None,
+        }
+    }
+
+    let mut offset = 0;
+    for (index, root) in old.roots().iter().enumerate() {
+        let folded = root
+            .flatten_visibles()
+            .iter()
+            .filter_map(fold_state)
+            .collect::<HashMap<_, _>>();
+        let Some(mut kinds) = stream.roots().get(index).map(JsonNode::flatten_visibles) else {
+            return;
+        };
+        // Going down from the top, so that unfolding a node brings its children in
+        // before they are reached.
+        let mut i = 0;
+        while i < kinds.len() {
+            let differs = fold_state(&kinds[i])
+                .is_some_and(|(path, state)| folded.get(&path).is_some_and(|&old| old != state));
+            if differs {
+                stream.move_to_head();
+                stream.shift(0, offset + i);
+                stream.toggle();
+                kinds = stream.roots()[index].flatten_visibles();
+            }
+            i += 1;
+        }
+        offset += kinds.len();
+    }
+    stream.move_to_head();
+}
+
+/// Converts `node` back into the JSON value it was built from.
+pub fn to_value(node: &JsonNode) -> serde_json::Value {
+    match node {
+        JsonNode::Object { children, .. } => serde_json::Value::Object(
+            children
+                .iter()
+                .map(|(key, child)| (key.clone(), to_value(child)))
+                .collect(),
+        ),
+        JsonNode::Array { children, .. } => {
+            serde_json::Value::Array(children.iter().map(to_value).collect())
+        }
+        JsonNode::Leaf(value) => value.clone(),
+    }
+}
+
+/// Inserts `separator` between every group of three digits
+/// in the integer part of a JSON number, e.g. `-1234567.5` becomes `-1,234,567.5`.
+pub fn group_digits(number: &str, separator: char) -> String {
+    let (sign, unsigned) = match number.strip_prefix('-') {
+        Some(unsigned) => ("-", unsigned),
+        None => ("", number),
+    };
+    let end = unsigned
+        .find(|c: char| !c.is_ascii_digit())
+        .unwrap_or(unsigned.len());
+    let (integer, rest) = unsigned.split_at(end);
+
+    let mut grouped = String::new();
+    for (i, digit) in integer.chars().enumerate() {
+        if i > 0 && (integer.len() - i) % 3 == 0 {
+            grouped.push(separator);
+        }
+        grouped.push(digit);
+    }
+    format!("{}{}{}", sign, grouped, rest)
+}
+
+/// Shortens `text` to at most `max_width` display columns,
+/// replacing its start, middle or end with an ellipsis if it does not fit.
+pub fn truncate(text: &str, max_width: usize, ellipsis: Ellipsis) -> String {
+    let graphemes = StyledGraphemes::from(text);
+    if graphemes.widths() <= max_width {
+        return text.to_string();
+    }
+    // Columns left for the text besides the ellipsis.
+    let budget = max_width.saturating_sub(1);
+    let take = |iter: &mut dyn Iterator<Item = &StyledGrapheme>, budget: usize| {
+        let mut width = 0;
+        iter.take_while(|g| {
+            width += g.width();
+            width <= budget
+        })
+        .map(|g| g.to_string())
+        .collect::<Vec<_>>()
+    };
+    let head = |budget| take(&mut graphemes.iter(), budget).concat();
+    let tail = |budget| {
+        let mut tail = take(&mut graphemes.iter().rev(), budget);
+        tail.reverse();
+        tail.concat()
+    };
+    match ellipsis {
+        Ellipsis::Start => format!("…{}", tail(budget)),
+        Ellipsis::Middle => format!("{}…{}", head(budget - budget / 2), tail(budget / 2)),
+        Ellipsis::End => format!("{}…", head(budget)),
+    }
+}
+
+/// Returns the nesting level of the line `kind`.
+fn level(kind: &JsonSyntaxKind) -> usize {
+    match kind {
+        JsonSyntaxKind::MapStart { indent, .. }
+        | JsonSyntaxKind::MapEnd { indent, .. }
+        | JsonSyntaxKind::MapFolded { indent, .. }
+        | JsonSyntaxKind::MapEntry { indent, .. }
+        | JsonSyntaxKind::ArrayFolded { indent, .. }
+        | JsonSyntaxKind::ArrayStart { indent, .. }
+        | JsonSyntaxKind::ArrayEnd { indent, .. }
+        | JsonSyntaxKind::ArrayEntry { indent, .. } => *indent,
+    }
+}
+
+/// Builds the tree connectors (`├─`, `└─` and `│`) drawn in place of
+/// the indentation of each line in `kinds`, each level being `width` columns wide.
+pub fn tree_guides(kinds: &[JsonSyntaxKind], width: usize) -> Vec<String> {
+    // Whether each line is the last element of its parent.
+    // Start lines do not know it themselves, so it is taken from their end line.
+    let mut is_last = vec![true; kinds.len()];
+    let mut starts = vec![];
+    for (i, kind) in kinds.iter().enumerate() {
+        match kind {
+            JsonSyntaxKind::MapStart { .. } | JsonSyntaxKind::ArrayStart { .. } => starts.push(i),
+            JsonSyntaxKind::MapEnd { is_last: last, .. }
+            | JsonSyntaxKind::ArrayEnd { is_last: last, .. } => {
+                if let Some(start) = starts.pop() {
+                    is_last[start] = *last;
+                }
+                is_last[i] = *last;
+            }
+            JsonSyntaxKind::MapFolded { is_last: last, .. }
+            | JsonSyntaxKind::MapEntry { is_last: last, .. }
+            | JsonSyntaxKind::ArrayFolded { is_last: last, .. }
+            | JsonSyntaxKind::ArrayEntry { is_last: last, .. } => is_last[i] = *last,
+        }
+    }
+
+    let pad = |glyph: &str| format!("{:<width$}", glyph, width = width);
+    // Whether the element at each level has siblings below it.
+    let mut continues = vec![];
+    kinds
+        .iter()
+        .enumerate()
+        .map(|(i, kind)| {
+            let level = level(kind);
+            let is_end = matches!(
+                kind,
+                JsonSyntaxKind::MapEnd { .. } | JsonSyntaxKind::ArrayEnd { .. }
+            );
+            if level == 0 {
+                return String::new();
+            }
+            continues.resize(level, false);
+            let ancestors = if is_end { level } else { level - 1 };
+            let mut guide = continues[..ancestors]
+                .iter()
+                .map(|continues| pad(if *continues { "│" } else { "" }))
+                .collect::<String>();
+            if !is_end {
+                guide.push_str(&pad(if is_last[i] { "└─" } else { "├─" }));
+                continues[level - 1] = !is_last[i];
+            }
+            guide
+        })
+        .collect()
+}
+
+/// Splits `row` into rows of at most `width` columns,
+/// indenting all but the first by `hang` columns.
+/// The indentation is dropped if it would take more than half of the width.
+pub fn wrap(row: &StyledGraphemes, width: usize, hang: usize) -> Vec<StyledGraphemes> {
+    let hang = if hang * 2 > width { 0 } else { hang };
+    let mut rows = vec![StyledGraphemes::default()];
+    let mut used = 0;
+    for g in row.iter() {
+        let last = rows.len() - 1;
+        if used + g.width() > width && !rows[last].is_empty() && used > hang {
+            rows.push(StyledGraphemes::from(" ".repeat(hang)));
+            used = hang;
+        }
+        let last = rows.len() - 1;
+        rows[last].push_back(g.clone());
+        used += g.width();
+    }
+    rows
+}
+
+/// Splits `row` at its newlines, which raw strings may contain.
+pub fn split_lines(row: &StyledGraphemes) -> Vec<StyledGraphemes> {
+    let mut lines = vec![StyledGraphemes::default()];
+    for g in row.iter() {
+        if g.to_string() == "\n" {
+            lines.push(StyledGraphemes::default());
+        } else {
+            let last = lines.len() - 1;
+            lines[last].push_back(g.clone());
+        }
+    }
+    lines
+}
+
+/// Returns the number shown in the gutter for the `line`-th line (from 0)
+/// when the cursor is on the `cursor`-th line.
+/// Relative numbers count the distance from the cursor,
+/// except on the cursor line which shows its absolute number.
+pub fn line_number(line: usize, cursor: usize, mode: LineNumbers) -> usize {
+    match mode {
+        LineNumbers::Relative if line != cursor => line.abs_diff(cursor),
+        LineNumbers::Absolute | LineNumbers::Relative => line + 1,
+    }
+}
+
+/// Returns the rows of a scrollbar track of `track` rows occupied by the thumb,
+/// for a viewport showing `visible` of `total` lines starting at line `offset`.
+pub fn scrollbar_thumb(
+    track: usize,
+    total: usize,
+    offset: usize,
+    visible: usize,
+) -> (usize, usize) {
+    if total == 0 {
+        return (0, track);
+    }
+    let size = (visible * track).div_ceil(total).clamp(1, track);
+    let start = (offset * track / total).min(track - size);
+    (start, start + size)
+}
+
+/// Returns the number of lines `value` takes when fully expanded.
+fn line_count(value: &serde_json::Value) -> usize {
+    match value {
+        serde_json::Value::Object(map) if !map.is_empty() => {
+            2 + map.values().map(line_count).sum::<usize>()
+        }
+        serde_json::Value::Array(array) if !array.is_empty() => {
+            2 + array.iter().map(line_count).sum::<usize>()
+        }
+        _ => 1,
+    }
+}
+
+/// Splits `values` into pages of at most `max_lines` lines when fully expanded,
+/// returned as the range of indexes of the values on each page.
+/// A value longer than that makes up a page on its own.
+pub fn paginate(values: &[serde_json::Value], max_lines: usize) -> Vec<Range<usize>> {
+    let mut pages = vec![];
+    let mut start = 0;
+    let mut lines = 0;
+    for (i, value) in values.iter().enumerate() {
+        let count = line_count(value);
+        if i > start && lines + count > max_lines {
+            pages.push(start..i);
+            start = i;
+            lines = 0;
+        }
+        lines += count;
+    }
+    if start < values.len() || pages.is_empty() {
+        pages.push(start..values.len());
+    }
+    pages
+}
+
+/// The JSON viewer, which renders the visible lines of a `JsonStream`
+/// starting from the line under the cursor.
+#[derive(Clone)]
+pub struct State {
+    pub stream: Stream,
+    pub theme: JsonTheme,
+    /// The text searched for in the results, highlighted where it matches.
+    pub find: Option<Regex>,
+    /// The line shown on each row of the pane last created, to map mouse clicks back to lines.
+    pub row_lines: RefCell<Vec<usize>>,
+    /// Whether the values are left over from an earlier filter because the current one failed.
+    /// They are all dimmed, the line under the cursor included.
+    pub stale: bool,
+}
+
+impl State {
+    /// Returns the JSON values shown in the viewer.
+    pub fn values(&self) -> Vec<serde_json::Value> {
+        self.stream.roots().iter().map(to_value).collect()
+    }
+
+    /// Returns every line of the values as the viewer shows them, without a cursor.
+    pub fn lines(&self) -> Vec<StyledGraphemes> {
+        let kinds = self.stream.flatten_kinds();
+        let guides = if self.theme.tree_guides {
+            tree_guides(&kinds, self.theme.indent.max(2))
+        } else {
+            vec![]
+        };
+        kinds
+            .iter()
+            .enumerate()
+            .map(|(i, kind)| {
+                let indent = match guides.get(i) {
+                    Some(guide) => StyledGraphemes::from_str(guide, self.theme.tree_guide_style),
+                    None => StyledGraphemes::from(" ".repeat(self.indent_level(kind))),
+                };
+                StyledGraphemes::from_iter([indent, self.gen_syntax_style(kind)])
+            })
+            .collect()
+    }
+
+    /// Returns the indices of the lines whose text (without indentation) matches `pattern`.
+    pub fn find_lines(&self, pattern: &Regex) -> Vec<usize> {
+        self.stream
+            .flatten_kinds()
+            .iter()
+            .enumerate()
+            .filter(|(_, kind)| pattern.is_match(&self.gen_syntax_style(kind).to_string()))
+            .map(|(i, _)| i)
+            .collect()
+    }
+
+    fn indent_level(&self, kind: &JsonSyntaxKind) -> usize {
+        match kind {
+            JsonSyntaxKind::MapStart { indent, .. }
+            | JsonSyntaxKind::MapEnd { indent, .. }
+            | JsonSyntaxKind::MapFolded { indent, .. }
+            | JsonSyntaxKind::MapEntry { indent, .. }
+            | JsonSyntaxKind::ArrayFolded { indent, .. }
+            | JsonSyntaxKind::ArrayStart { indent, .. }
+            | JsonSyntaxKind::ArrayEnd { indent, .. }
+            | JsonSyntaxKind::ArrayEntry { indent, .. } => *indent * self.theme.indent,
+        }
+    }
+
+    fn format_value(&self, v: &serde_json::Value) -> StyledGraphemes {
+        if self.theme.keys_only {
+            return self.format_type(v);
+        }
+        match v {
+            serde_json::Value::String(s) => {
+                let s = match self.theme.max_value_width {
+                    Some(max_width) => truncate(s, max_width, self.theme.ellipsis),
+                    None => s.clone(),
+                };
+                StyledGraphemes::from_str(format!("\"{}\"", s), self.theme.string_value_style)
+            }
+            serde_json::Value::Number(n) => {
+                let number = match self.theme.number_separator {
+                    Some(separator) => group_digits(&n.to_string(), separator),
+                    None => n.to_string(),
+                };
+                let number = match self.theme.max_value_width {
+                    Some(max_width) => truncate(&number, max_width, self.theme.ellipsis),
+                    None => number,
+                };
+                StyledGraphemes::from_str(number, self.theme.number_value_style)
+            }
+            serde_json::Value::Bool(b) => {
+                StyledGraphemes::from_str(b.to_string(), self.theme.boolean_value_style)
+            }
+            serde_json::Value::Null => {
+                StyledGraphemes::from_str("null", self.theme.null_value_style)
+            }
+            _ => StyledGraphemes::from(""),
+        }
+    }
+
+    /// Shows the type of a scalar value in place of the value, for `keys_only`.
+    fn format_type(&self, v: &serde_json::Value) -> StyledGraphemes {
+        match v {
+            serde_json::Value::String(_) => {
+                StyledGraphemes::from_str("str", self.theme.string_value_style)
+            }
+            serde_json::Value::Number(_) => {
+                StyledGraphemes::from_str("num", self.theme.number_value_style)
+            }
+            serde_json::Value::Bool(_) => {
+                StyledGraphemes::from_str("bool", self.theme.boolean_value_style)
+            }
+            serde_json::Value::Null => {
+                StyledGraphemes::from_str("null", self.theme.null_value_style)
+            }
+            _ => StyledGraphemes::from(""),
+        }
+    }
+
+    fn format_key(&self, key: &Option<String>, token: StyledGraphemes) -> StyledGraphemes {
+        match key {
+            Some(key) => StyledGraphemes::from_iter([
+                StyledGraphemes::from_str(
+                    match self.theme.max_key_width {
+                        Some(max_width) => {
+                            format!("\"{}\"", truncate(key, max_width, self.theme.ellipsis))
+                        }
+                        None => format!("\"{}\"", key),
+                    },
+                    self.theme.key_style,
+                ),
+                StyledGraphemes::from(": "),
+                token,
+            ]),
+            None => token,
+        }
+    }
+
+    fn gen_syntax_style(&self, kind: &JsonSyntaxKind) -> StyledGraphemes {
+        let (token, is_last) = match kind {
+            JsonSyntaxKind::MapStart { key, .. } => (
+                self.format_key(
+                    key,
+                    StyledGraphemes::from_str("{", self.theme.curly_brackets_style),
+                ),
+                true,
+            ),
+            JsonSyntaxKind::MapEnd { is_last, .. } => (
+                StyledGraphemes::from_str("}", self.theme.curly_brackets_style),
+                *is_last,
+            ),
+            JsonSyntaxKind::MapFolded { key, is_last, .. } => (
+                self.format_key(
+                    key,
+                    StyledGraphemes::from_str("{...}", self.theme.curly_brackets_style),
+                ),
+                *is_last,
+            ),
+            JsonSyntaxKind::MapEntry { kv, is_last, .. } => (
+                self.format_key(&Some(kv.0.clone()), self.format_value(&kv.1)),
+                *is_last,
+            ),
+            JsonSyntaxKind::ArrayStart { key, .. } => (
+                self.format_key(
+                    key,
+                    StyledGraphemes::from_str("[", self.theme.square_brackets_style),
+                ),
+                true,
+            ),
+            JsonSyntaxKind::ArrayEnd { is_last, .. } => (
+                StyledGraphemes::from_str("]", self.theme.square_brackets_style),
+                *is_last,
+            ),
+            JsonSyntaxKind::ArrayFolded { key, is_last, .. } => (
+                self.format_key(
+                    key,
+                    StyledGraphemes::from_str("[...]", self.theme.square_brackets_style),
+                ),
+                *is_last,
+            ),
+            JsonSyntaxKind::ArrayEntry {
+                v: serde_json::Value::String(s),
+                path,
+                ..
+            } if self.theme.raw_output && !self.theme.keys_only && path.is_empty() => (
+                StyledGraphemes::from_str(s, self.theme.string_value_style),
+                true,
+            ),
+            JsonSyntaxKind::ArrayEntry { v, is_last, .. } => (self.format_value(v), *is_last),
+        };
+
+        if is_last {
+            token
+        } else {
+            StyledGraphemes::from_iter([token, StyledGraphemes::from(",")])
+        }
+    }
+}
+
+impl PaneFactory for State {
+    fn create_pane(&self, width: u16, height: u16) -> Pane {
+        let height = match self.theme.lines {
+            Some(lines) => lines.min(height as usize),
+            None => height as usize,
+        };
+        let position = self.stream.position();
+        let kinds = self.stream.flatten_kinds();
+        let scrollbar = self.theme.scrollbar && kinds.len() > height && width > 1;
+        let content_width = if scrollbar { width - 1 } else { width } as usize;
+
+        // Right-aligned line numbers followed by a space.
+        let gutter_width = match self.theme.line_numbers {
+            Some(_) => kinds.len().to_string().len() + 1,
+            None => 0,
+        };
+        let text_width = content_width.saturating_sub(gutter_width).max(1);
+
+        let guides = if self.theme.tree_guides {
+            tree_guides(&kinds, self.theme.indent.max(2))
+        } else {
+            vec![]
+        };
+
+        let (lines, mut matrix) = kinds
+            .iter()
+            .enumerate()
+            .skip(position)
+            .take(height)
+            .flat_map(|(i, kind)| {
+                let indent = match guides.get(i) {
+                    Some(guide) => StyledGraphemes::from_str(guide, self.theme.tree_guide_style),
+                    None => StyledGraphemes::from(" ".repeat(self.indent_level(kind))),
+                };
+                let indent_width = indent.widths();
+                let syntax = match &self.find {
+                    Some(pattern) => find::highlight(
+                        self.gen_syntax_style(kind),
+                        pattern,
+                        self.theme.match_style,
+                    ),
+                    None => self.gen_syntax_style(kind),
+                };
+                let row = if i == position {
+                    StyledGraphemes::from_iter([
+                        indent,
+                        syntax.apply_attribute(if self.stale {
+                            Attribute::Dim
+                        } else {
+                            self.theme.active_item_attribute
+                        }),
+                    ])
+                } else {
+                    StyledGraphemes::from_iter([indent, syntax])
+                        .apply_attribute(self.theme.inactive_item_attribute)
+                };
+                let rows = split_lines(&row)
+                    .iter()
+                    .flat_map(|line| {
+                        if self.theme.wrap_values {
+                            // Align the wrapped rows with the start of the value.
+                            let key = match kind {
+                                JsonSyntaxKind::MapEntry { kv, .. } => self
+                                    .format_key(&Some(kv.0.clone()), StyledGraphemes::default())
+                                    .widths(),
+                                _ => 0,
+                            };
+                            wrap(line, text_width, indent_width + key)
+                        } else {
+                            line.matrixify(text_width, height, 0).0
+                        }
+                    })
+                    .collect::<Vec<_>>();
+                let rows: Vec<StyledGraphemes> = match self.theme.line_numbers {
+                    Some(mode) => rows
+                        .into_iter()
+                        .enumerate()
+                        .map(|(j, row)| {
+                            // Only the first of the wrapped rows is numbered.
+                            let number = if j == 0 {
+                                line_number(i, position, mode).to_string()
+                            } else {
+                                String::new()
+                            };
+                            StyledGraphemes::from_iter([
+                                StyledGraphemes::from_str(
+                                    format!("{:>1$} ", number, gutter_width - 1),
+                                    self.theme.line_number_style,
+                                ),
+                                row,
+                            ])
+                        })
+                        .collect(),
+                    None => rows,
+                };
+                rows.into_iter().map(move |row| (i, row))
+            })
+            .take(height)
+            .unzip::<_, _, Vec<_>, Vec<_>>();
+        *self.row_lines.borrow_mut() = lines;
+
+        if scrollbar {
+            let (start, end) = scrollbar_thumb(
+                matrix.len(),
+                kinds.len(),
+                position,
+                height.min(kinds.len() - position),
+            );
+            let style = StyleBuilder::new().fgc(Color::DarkGrey).build();
+            for (i, row) in matrix.iter_mut().enumerate() {
+                let padding = " ".repeat(content_width.saturating_sub(row.widths()));
+                let bar = if (start..end).contains(&i) {
+                    "┃"
+                } else {
+                    "│"
+                };
+                *row = StyledGraphemes::from_iter([
+                    row.clone(),
+                    StyledGraphemes::from(padding),
+                    StyledGraphemes::from_str(bar, style),
+                ]);
+            }
+        }
+
+        Pane::new(matrix, 0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    mod truncate {
+        use super::*;
+
+        #[test]
+        fn test_short_key() {
+            assert_eq!("name", truncate("name", 4, Ellipsis::End));
+        }
+
+        #[test]
+        fn test_long_key() {
+            assert_eq!("0f8f…", truncate("0f8fad5b-d9cb-469f", 5, Ellipsis::End));
+        }
+
+        #[test]
+        fn test_wide_characters() {
+            assert_eq!("日本…", truncate("日本語のキー", 6, Ellipsis::End));
+            assert_eq!("日…ー", truncate("日本語のキー", 6, Ellipsis::Middle));
+        }
+
+        #[test]
+        fn test_start() {
+            assert_eq!("…469f", truncate("0f8fad5b-d9cb-469f", 5, Ellipsis::Start));
+        }
+
+        #[test]
+        fn test_middle() {
+            assert_eq!(
+                "0f8…69f",
+                truncate("0f8fad5b-d9cb-469f", 7, Ellipsis::Middle)
+            );
+            assert_eq!(
+                "0f8…9f",
+                truncate("0f8fad5b-d9cb-469f", 6, Ellipsis::Middle)
+            );
+        }
+    }
+
+    mod tree_guides {
+        use super::*;
+
+        #[test]
+        fn test() {
+            let value = serde_json::json!({"a": [1, 2], "b": {"c": null}});
+            let kinds = JsonStream::new([value], None).flatten_kinds();
+            assert_eq!(
+                vec![
+                    "",
+                    "├─ ",
+                    "│  ├─ ",
+                    "│  └─ ",
+                    "│  ",
+                    "└─ ",
+                    "   └─ ",
+                    "   ",
+                    ""
+                ],
+                tree_guides(&kinds, 3)
+            );
+        }
+    }
+
+    mod to_value {
+        use super::*;
+
+        #[test]
+        fn test_round_trip() {
+            let value = serde_json::json!({"a": [1, {"b": null}], "c": "d"});
+            assert_eq!(value, to_value(&JsonNode::new(value.clone(), Some(0))));
+        }
+    }
+
+    mod stream {
+        use super::*;
+
+        /// Counts the lines above the cursor by stepping a copy of the stream back.
+        fn counted(stream: &JsonStream) -> usize {
+            let mut probe = stream.clone();
+            let mut position = 0;
+            while probe.backward() {
+                position += 1;
+            }
+            position
+        }
+
+        #[test]
+        fn test_position() {
+            let value = serde_json::json!({"a": {"b": [1, 2, 3]}, "c": [4, {"d": 5}]});
+            let mut inner = JsonStream::new([value.clone(), value], None);
+            inner.shift(0, 13);
+            let mut stream = Stream::from(inner);
+            assert_eq!(13, stream.position());
+
+            stream.backward();
+            stream.shift(2, 5);
+            stream.toggle();
+            stream.forward();
+            assert_eq!(counted(&stream), stream.position());
+            stream.move_to_tail();
+            assert_eq!(counted(&stream), stream.position());
+            stream.collapse_all();
+            assert_eq!(counted(&stream), stream.position());
+            stream.move_to_head();
+            stream.shift(0, 1);
+            stream.toggle();
+            assert_eq!(1, stream.position());
+            assert!(!stream.shift(2, 0));
+            assert_eq!(counted(&stream), stream.position());
+        }
+    }
+
+    mod restore_folds {
+        use super::*;
+
+        #[test]
+        fn test() {
+            let value = serde_json::json!({"a": {"b": 1}, "c": [2]});
+            let mut old = JsonStream::new([value.clone()], None);
+            // Fold "a" and unfold nothing else.
+            old.shift(0, 1);
+            old.toggle();
+
+            let mut stream = Stream::from(JsonStream::new([value.clone(), value], None));
+            restore_folds(&mut stream, &old);
+            let folded = stream
+                .flatten_kinds()
+                .iter()
+                .filter(|kind| matches!(kind, JsonSyntaxKind::MapFolded { .. }))
+                .count();
+            assert_eq!(1, folded);
+            assert_eq!(old.roots()[0], stream.roots()[0]);
+            assert_eq!(
+                &JsonNode::new(serde_json::json!({"a": {"b": 1}, "c": [2]}), None),
+                &stream.roots()[1]
+            );
+        }
+    }
+
+    mod wrap {
+        use super::*;
+
+        fn texts(rows: Vec<StyledGraphemes>) -> Vec<String> {
+            rows.iter().map(|row| row.to_string()).collect()
+        }
+
+        #[test]
+        fn test_fits() {
+            assert_eq!(vec!["abc"], texts(wrap(&"abc".into(), 3, 1)));
+        }
+
+        #[test]
+        fn test_hang() {
+            assert_eq!(
+                vec!["  \"k\": \"abcdefgh", "        ijklmnop", "        \""],
+                texts(wrap(&"  \"k\": \"abcdefghijklmnop\"".into(), 16, 8))
+            );
+        }
+
+        #[test]
+        fn test_hang_too_wide() {
+            assert_eq!(vec!["abcd", "efgh"], texts(wrap(&"abcdefgh".into(), 4, 3)));
+        }
+    }
+
+    mod split_lines {
+        use super::*;
+
+        #[test]
+        fn test() {
+            assert_eq!(
+                vec!["a", "", "b"],
+                split_lines(&"a\n\nb".into())
+                    .iter()
+                    .map(|line| line.to_string())
+                    .collect::<Vec<_>>()
+            );
+        }
+    }
+
+    mod line_number {
+        use super::*;
+
+        #[test]
+        fn test_absolute() {
+            assert_eq!(8, line_number(7, 3, LineNumbers::Absolute));
+        }
+
+        #[test]
+        fn test_relative() {
+            assert_eq!(4, line_number(7, 3, LineNumbers::Relative));
+            assert_eq!(2, line_number(1, 3, LineNumbers::Relative));
+            assert_eq!(4, line_number(3, 3, LineNumbers::Relative));
+        }
+    }
+
+    mod scrollbar_thumb {
+        use super::*;
+
+        #[test]
+        fn test_top() {
+            assert_eq!((0, 2), scrollbar_thumb(10, 50, 0, 10));
+        }
+
+        #[test]
+        fn test_middle_and_bottom() {
+            assert_eq!((5, 7), scrollbar_thumb(10, 50, 25, 10));
+            assert_eq!((9, 10), scrollbar_thumb(10, 50, 49, 1));
+        }
+
+        #[test]
+        fn test_minimum_size() {
+            assert_eq!((5, 6), scrollbar_thumb(10, 10_000, 5_000, 10));
+        }
+    }
+
+    mod paginate {
+        use super::*;
+
+        #[test]
+        fn test() {
+            let values = vec![
+                serde_json::json!({"a": 1}),
+                serde_json::json!([1, 2]),
+                serde_json::json!(null),
+            ];
+            assert_eq!(vec![0..1, 1..3], paginate(&values, 5));
+        }
+
+        #[test]
+        fn test_long_value() {
+            let values = vec![serde_json::json!([1, 2, 3]), serde_json::json!(1)];
+            assert_eq!(vec![0..1, 1..2], paginate(&values, 2));
+        }
+
+        #[test]
+        fn test_empty() {
+            assert_eq!(vec![0..0], paginate(&[], 5));
+        }
+    }
+
+    mod group_digits {
+        use super::*;
+
+        #[test]
+        fn test_short_number() {
+            assert_eq!("999", group_digits("999", ','));
+        }
+
+        #[test]
+        fn test_integer() {
+            assert_eq!("1,000,000", group_digits("1000000", ','));
+            assert_eq!("-12_345", group_digits("-12345", '_'));
+        }
+
+        #[test]
+        fn test_fraction_and_exponent() {
+            assert_eq!("1,234.5678", group_digits("1234.5678", ','));
+            assert_eq!("12,345e10", group_digits("12345e10", ','));
+        }
+    }
+}
@@ -0,0 +1,59 @@
+use std::{io, process::Command};
+
+use anyhow::{anyhow, Result};
+use promkit::serde_json::{self, Deserializer};
+
+use crate::process;
+
+/// Runs `query` against the `inputs` with the `jq` binary on PATH, with each of
+/// `vars` bound to `$name`, like `jq -c` does on a stream of inputs.
+/// The query is passed as it is, so that `import` and `include` work.
+/// If jq fails, the error is what jq printed on its standard error.
+pub fn run(
+    query: &str,
+    inputs: &[serde_json::Value],
+    vars: &[(String, serde_json::Value)],
+) -> Result<Vec<serde_json::Value>> {
+    let mut command = Command::new("jq");
+    command.arg("-c");
+    for (name, value) in vars {
+        match value {
+            serde_json::Value::String(s) => command.args(["--arg", name, s]),
+            value => command.args(["--argjson", name, &value.to_string()]),
+        };
+    }
+    let input = inputs
+        .iter()
+        .map(|value| format!("{}\n", value))
+        .collect::<String>();
+    command.arg(query);
+    let output =
+        process::pipe_through(&mut command, input.into_bytes()).map_err(|e| match e.kind() {
+            io::ErrorKind::NotFound => anyhow!("jq was not found on PATH"),
+            _ => anyhow!("Failed to run jq: {}", e),
+        })?;
+    if !output.status.success() {
+        return Err(anyhow!(
+            "{}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+
+    Deserializer::from_slice(&output.stdout)
+        .into_iter::<serde_json::Value>()
+        .map(|result| result.map_err(anyhow::Error::from))
+        .collect()
+}
+
+/// Runs `query` like [`run`], but keeps the results of each input separate
+/// by running jq once per input.
+pub fn run_each(
+    query: &str,
+    inputs: &[serde_json::Value],
+    vars: &[(String, serde_json::Value)],
+) -> Result<Vec<Vec<serde_json::Value>>> {
+    inputs
+        .iter()
+        .map(|input| run(query, std::slice::from_ref(input), vars))
+        .collect()
+}
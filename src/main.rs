@@ -1,27 +1,52 @@
 use std::{
     collections::HashSet,
-    fs::File,
-    io::{self, Read},
+    env,
+    fs::{self, File},
+    io::{self, IsTerminal, Read, Write},
+    iter,
     path::PathBuf,
+    sync::mpsc,
+    thread,
+    time::{Duration, Instant},
 };
 
 use anyhow::{anyhow, Result};
 use clap::Parser;
 
 use promkit::{
-    crossterm::style::{Attribute, Attributes, Color},
+    crossterm::{
+        style::{self, Attribute, Attributes, Color},
+        terminal,
+    },
     listbox,
     serde_json::{self, Deserializer},
     style::StyleBuilder,
-    text, text_editor,
+    text,
+    text_editor::{self, TextEditor},
 };
 
+mod archive;
+mod browser;
+mod clipboard;
+mod csv;
+mod depth;
+mod gzip;
 mod jnv;
-use jnv::{Jnv, JsonTheme};
+mod jq;
+use jnv::{
+    completion_paths, palette, CompletionScope, Ellipsis, InputLoader, InputSources, Jnv,
+    JqBackend, JsonTheme, KeybindPreset, LineNumbers, NewlineMode, SearchMode, SuggestionPlacement,
+    SuggestionSort,
+};
+mod path;
+mod process;
+mod query;
+mod shell;
+mod state;
 mod trie;
 
 /// JSON navigator and interactive filter leveraging jq
-#[derive(Parser)]
+#[derive(Clone, Parser)]
 #[command(
     name = "jnv",
     version,
@@ -37,6 +62,9 @@ Examples:
 - Read from standard input:
         cat data.json | {bin}
 
+- Read from an argument:
+        {bin} --input-json '[1, 2, 3]'
+
 Arguments:
 {positionals}
 
@@ -45,14 +73,148 @@ Options:
 "
 )]
 pub struct Args {
-    /// Optional path to a JSON file.
+    /// Optional paths to JSON files, whose values are concatenated in order.
     /// If not provided or if "-" is specified,
     /// reads from standard input.
-    pub input: Option<PathBuf>,
+    /// Gzip-compressed input is decompressed with `gzip` on PATH.
+    pub input: Vec<PathBuf>,
+
+    #[arg(
+        short = 'q',
+        long = "query",
+        value_name = "JQ",
+        group = "batch_query",
+        help = "jq filter to start with (or to run with --batch).",
+        long_help = "
+        Sets the filter that the editor starts with, taking precedence over
+        the JNV_QUERY environment variable. With --batch, this filter is run
+        once without starting the interface.
+        "
+    )]
+    pub query: Option<String>,
+
+    #[arg(
+        long = "query-file",
+        value_name = "PATH",
+        group = "batch_query",
+        help = "Reads the jq filter to start with (or to run with --batch) from a file.",
+        long_help = "
+        Like --query, but reads the filter from the file at PATH, e.g. to keep
+        long filters around. Lines starting with `#` are jq comments and are
+        left out, as is trailing whitespace. Takes precedence over
+        --seed-query and the JNV_QUERY environment variable.
+        "
+    )]
+    pub query_file: Option<PathBuf>,
+
+    #[arg(
+        long = "seed-query",
+        value_name = "JQ",
+        conflicts_with_all = ["query", "batch"],
+        help = "jq filter to prefill the editor with.",
+        long_help = "
+        Sets the filter that the editor starts with and runs it right away,
+        like --query, but only ever for the interface: it cannot be combined
+        with --batch, so shell aliases using it always stay interactive.
+        Takes precedence over the JNV_QUERY environment variable.
+        "
+    )]
+    pub seed_query: Option<String>,
+
+    #[arg(
+        long = "restore-last-query",
+        help = "Starts with the last successful filter used on the same input file.",
+        long_help = "
+        Remembers the last filter that ran successfully for each input file
+        and starts with it the next time the same file is opened, unless
+        --query, --seed-query or JNV_QUERY is given. The filters are kept in
+        $XDG_STATE_HOME/jnv/last_queries.json (or ~/.local/state/jnv/).
+        Has no effect on standard input, which cannot be told apart between runs.
+        "
+    )]
+    pub restore_last_query: bool,
+
+    #[arg(
+        long = "persist-pins",
+        help = "Keeps the filters pinned with Alt+K between runs.",
+        long_help = "
+        Saves the filters pinned with Alt+K (and cycled through with Alt+, and Alt+.)
+        to $XDG_STATE_HOME/jnv/pinned_queries.json (or ~/.local/state/jnv/)
+        and starts with them pinned the next time. Without this option,
+        the pins are forgotten on exit.
+        "
+    )]
+    pub persist_pins: bool,
+
+    #[arg(
+        long = "batch",
+        requires = "batch_query",
+        help = "Runs --query once, prints the result and exits.",
+        long_help = "
+        Runs the filter given with --query (or --query-file) against the input without starting
+        the interface, prints the result to stdout and exits, e.g. to check
+        JSON transforms in CI. The output is colored like the JSON viewer
+        if stdout is a terminal, and plain JSON otherwise.
+        Exits with a non-zero status if the filter fails.
+        "
+    )]
+    pub batch: bool,
+
+    #[arg(
+        long = "stream-output",
+        requires = "batch",
+        help = "With --batch, prints each result as soon as it is produced.",
+        long_help = "
+        With --batch, runs the filter on one input value at a time and prints
+        and flushes each result as soon as it is produced, instead of
+        printing the whole result once the filter has finished, so that
+        huge outputs can be piped into other commands like with jq.
+        "
+    )]
+    pub stream_output: bool,
+
+    #[arg(
+        long = "arg",
+        num_args = 2,
+        value_names = ["NAME", "VALUE"],
+        help = "Binds $NAME to the string VALUE in filters, like jq (can be repeated).",
+        long_help = "
+        Binds the variable $NAME to the string VALUE for every filter
+        during the session, like `jq --arg`. Can be given multiple times.
+        "
+    )]
+    pub arg: Vec<String>,
+
+    #[arg(
+        long = "argjson",
+        num_args = 2,
+        value_names = ["NAME", "JSON"],
+        help = "Binds $NAME to the JSON value in filters, like jq (can be repeated).",
+        long_help = "
+        Binds the variable $NAME to the given JSON value for every filter
+        during the session, like `jq --argjson`. Can be given multiple times.
+        "
+    )]
+    pub argjson: Vec<String>,
+
+    #[arg(
+        long = "library",
+        value_name = "PATH",
+        env = "JNV_LIBRARY",
+        help = "Reads jq function definitions to use in every filter from a file.",
+        long_help = "
+        Prepends the contents of the file at PATH, e.g. `def pretty: ...;`,
+        to every filter during the session (and with --batch), so that
+        reusable helper functions are always in scope. The file is checked
+        once at startup and jnv exits if it does not parse.
+        "
+    )]
+    pub library: Option<PathBuf>,
 
     #[arg(
         short = 'e',
         long = "edit-mode",
+        env = "JNV_EDIT_MODE",
         default_value = "insert",
         value_parser = edit_mode_validator,
         help = "Edit mode for the interface ('insert' or 'overwrite').",
@@ -68,6 +230,7 @@ pub struct Args {
     #[arg(
         short = 'i',
         long = "indent",
+        env = "JNV_INDENT",
         default_value = "2",
         help = "Number of spaces used for indentation in the visualized data.",
         long_help = "
@@ -77,9 +240,25 @@ pub struct Args {
     )]
     pub indent: usize,
 
+    #[arg(
+        long = "indent-char",
+        env = "JNV_INDENT_CHAR",
+        value_name = "CHAR",
+        default_value = "space",
+        value_parser = indent_char_validator,
+        help = "Indents with spaces or tabs ('space' or 'tab').",
+        long_help = "
+        Selects the character used for indentation. With 'tab', results copied,
+        saved or written out are indented with one tab per level, like `jq --tab`.
+        The JSON viewer cannot draw tabs, so it shows each one as --indent columns.
+        "
+    )]
+    pub indent_char: char,
+
     #[arg(
         short = 'n',
         long = "no-hint",
+        env = "JNV_NO_HINT",
         help = "Disables the display of hints.",
         long_help = "
         When this option is enabled, it prevents the display of
@@ -91,10 +270,14 @@ pub struct Args {
     #[arg(
         short = 'd',
         long = "expand-depth",
+        env = "JNV_EXPAND_DEPTH",
         default_value = "3",
         help = "Initial depth to which JSON nodes are expanded in the visualization.",
         long_help = "
         Specifies the initial depth to which JSON nodes are expanded in the visualization.
+        Nodes deeper than this are shown collapsed, and can be expanded with Enter
+        or Ctrl+P: 0 collapses each value entirely, 1 shows only its top-level
+        keys or elements. This also applies to the result of every filter.
         Note: Increasing this depth can significantly slow down the display for large datasets.
         "
     )]
@@ -103,6 +286,7 @@ pub struct Args {
     #[arg(
         short = 's',
         long = "limit-length",
+        env = "JNV_LIMIT_LENGTH",
         default_value = "50",
         help = "Limit length of JSON array in the visualization.",
         long_help = "
@@ -112,9 +296,28 @@ pub struct Args {
     )]
     pub json_limit_length: Option<usize>,
 
+    #[arg(
+        long = "max-bytes",
+        value_name = "SIZE",
+        value_parser = byte_size_validator,
+        help = "Reads at most SIZE bytes of input (e.g. 500M).",
+        long_help = "
+        Stops reading the input after SIZE bytes in total, so that pointing jnv
+        at a huge file by mistake does not exhaust the memory. SIZE is a number
+        of bytes, optionally followed by K, M or G (powers of 1024).
+        The input is cut back to the end of its last complete JSON value
+        (or line, with --ndjson and --input-format csv) and a warning is shown.
+        Compressed input and tar archives larger than SIZE cannot be cut
+        and are rejected. Standard input is read no further than SIZE either,
+        also with --follow.
+        "
+    )]
+    pub max_bytes: Option<usize>,
+
     #[arg(
         short = 'l',
         long = "suggestion-list-length",
+        env = "JNV_SUGGESTION_LIST_LENGTH",
         default_value = "3",
         help = "Number of suggestions visible in the list.",
         long_help = "
@@ -123,6 +326,766 @@ pub struct Args {
         "
     )]
     pub suggestion_list_length: usize,
+
+    #[arg(
+        long = "max-depth",
+        default_value = "128",
+        help = "Maximum nesting depth of arrays and objects in the input.",
+        long_help = "
+        Rejects input whose arrays and objects are nested deeper than this depth
+        with an error as soon as the parser gets there,
+        instead of risking a stack overflow while processing it.
+        Deeply nested values take stack space to parse and show,
+        so a depth of many thousands may still overflow it.
+        "
+    )]
+    pub max_depth: usize,
+
+    #[arg(
+        long = "input-format",
+        value_name = "FORMAT",
+        default_value = "json",
+        value_parser = input_format_validator,
+        conflicts_with_all = ["ndjson", "follow"],
+        help = "Format of the input ('json' or 'csv').",
+        long_help = r#"
+        Selects how the input is parsed. Acceptable values are "json" or "csv".
+        - "json" reads one or more JSON values.
+        - "csv" reads a table with a header row, as one JSON object per row,
+          keyed by the header, so that --limit-length keeps the first N rows.
+          Fields are kept as strings.
+        "#,
+    )]
+    pub input_format: InputFormat,
+
+    #[arg(
+        long = "ndjson",
+        help = "Reads the input as newline-delimited JSON, one value per line.",
+        long_help = r#"
+        Parses each non-blank line of the input as a separate JSON value,
+        so that --limit-length keeps the first N records.
+        A malformed line does not abort loading; it is shown in its place as
+        {"error": ..., "line": ..., "text": ...} instead.
+        "#
+    )]
+    pub ndjson: bool,
+
+    #[arg(
+        long = "follow",
+        conflicts_with_all = ["input_json", "batch", "dump_paths"],
+        help = "Keeps reading new JSON values from standard input, like `tail -f`.",
+        long_help = "
+        Instead of reading standard input to the end before starting,
+        starts as soon as the first JSON value arrives and keeps appending
+        the values that follow, re-running the current filter on them.
+        This allows exploring a growing log, e.g. `tail -f app.log | jnv --follow`.
+        Reading stops at the first malformed value.
+        "
+    )]
+    pub follow: bool,
+
+    #[arg(
+        long = "slurp",
+        conflicts_with = "follow",
+        help = "Reads all input values into a single array, like `jq -s`.",
+        long_help = "
+        Collects the JSON values of all inputs into one array before filtering,
+        so that filters such as `length` or `.[0]` apply to the whole dataset
+        rather than to each value separately. --limit-length applies first:
+        the first N values are read, then wrapped in the array.
+        "
+    )]
+    pub slurp: bool,
+
+    #[arg(
+        long = "max-result-lines",
+        value_name = "N",
+        help = "Splits results into pages of at most N lines, switched with PageDown/PageUp.",
+        long_help = "
+        Shows large results one page at a time, so that only the values of the
+        current page are formatted. A page holds as many consecutive values as fit
+        in N lines when fully expanded; a longer value makes up a page on its own.
+        Press PageDown and PageUp to switch pages; the hint message and
+        --status-bar show the current page. Copying, saving and writing out
+        the result still include every page.
+        "
+    )]
+    pub max_result_lines: Option<usize>,
+
+    #[arg(
+        long = "max-filter-lines",
+        value_name = "N",
+        help = "Grows the filter editor up to N rows, scrolling to the cursor beyond.",
+        long_help = "
+        Limits the height of the filter editor, which otherwise grows with
+        long filters and with the lines started by Alt+Enter in multi-line filters.
+        Beyond N rows, the rows around the cursor are shown.
+        "
+    )]
+    pub max_filter_lines: Option<usize>,
+
+    #[arg(
+        long = "number-separator",
+        help = "Character inserted between groups of thousands in numbers.",
+        long_help = "
+        Displays numbers with the given character between groups of thousands,
+        e.g. `--number-separator ,` shows 1000000 as 1,000,000.
+        This only affects the visualization, not the JSON data itself.
+        "
+    )]
+    pub number_separator: Option<char>,
+
+    #[arg(
+        long = "no-follow-symlinks",
+        help = "Refuses to read the input file if it is a symbolic link.",
+        long_help = "
+        When this option is enabled, an input file that is a symbolic link
+        is rejected with an error instead of reading the file it points to.
+        "
+    )]
+    pub no_follow_symlinks: bool,
+
+    #[arg(
+        long = "pane-separator",
+        help = "Character for the line drawn between the filter editor and JSON viewer.",
+        long_help = "
+        Draws a horizontal line made of the given character (e.g. '─')
+        between the filter editor and the JSON viewer.
+        By default, no line is drawn.
+        "
+    )]
+    pub pane_separator: Option<char>,
+
+    #[arg(
+        long = "archive-glob",
+        default_value = "*.json",
+        help = "Glob selecting the members to load when the input is a tar or zip archive.",
+        long_help = "
+        When the input is a tar or zip archive, the members whose paths match this glob
+        are loaded and concatenated as multiple JSON structures.
+        Zip archives are extracted with the unzip command, which must be on PATH.
+        `*` matches any sequence of characters and `?` matches any single character.
+        "
+    )]
+    pub archive_glob: String,
+
+    #[arg(
+        long = "suggestion-placement",
+        env = "JNV_SUGGESTION_PLACEMENT",
+        default_value = "below",
+        value_parser = suggestion_placement_validator,
+        help = "Placement of the suggestion list ('above' or 'below' the filter editor).",
+        long_help = r#"
+        Specifies where the list of auto-completion suggestions is shown.
+        Acceptable values are "above" or "below".
+        - "above" shows the list above the filter editor.
+        - "below" shows the list below the filter editor and hint message.
+        "#,
+    )]
+    pub suggestion_placement: SuggestionPlacement,
+
+    #[arg(
+        long = "show-keys",
+        env = "JNV_SHOW_KEYS",
+        help = "Displays the recently pressed keys at the bottom.",
+        long_help = "
+        When this option is enabled, the keys pressed in the last few seconds
+        are displayed at the bottom of the interface (e.g. for screencasts).
+        "
+    )]
+    pub show_keys: bool,
+
+    #[arg(
+        long = "mouse",
+        env = "JNV_MOUSE",
+        help = "Scrolls and toggles nodes in the JSON viewer with the mouse.",
+        long_help = "
+        Captures the mouse: the wheel moves the cursor of the JSON viewer,
+        and clicking on a line moves the cursor there and expands or collapses it.
+        Off by default because, while captured, the terminal cannot select text
+        with the mouse (most terminals still do with Shift held).
+        "
+    )]
+    pub mouse: bool,
+
+    #[arg(
+        long = "status-bar",
+        env = "JNV_STATUS_BAR",
+        help = "Displays a status bar at the bottom.",
+        long_help = "
+        When this option is enabled, a line at the bottom of the interface
+        shows the number of input values, the path of the node under the cursor
+        and whether the current filter succeeded ('OK'), failed or returned
+        nothing ('ERR'), or is waiting for --debounce ('…').
+        "
+    )]
+    pub status_bar: bool,
+
+    #[arg(
+        long = "stats",
+        help = "Shows the size of the input and how long parsing it took.",
+        long_help = "
+        Reports how many bytes were read from the inputs, how many JSON values
+        they held and how long reading and parsing them took, to tell a slow start
+        from slow filters. The report is shown in the status bar (see --status-bar),
+        or printed to stderr with --batch. Not available with --follow.
+        "
+    )]
+    pub stats: bool,
+
+    #[arg(
+        long = "pipe-preview",
+        help = "Previews the result of each pipe stage of the query.",
+        long_help = "
+        When this option is enabled, the query is split at its top-level pipes ('|')
+        and the first result after each of the last few stages is displayed
+        below the hint message. This evaluates the query once per stage,
+        so it may slow down typing on large inputs.
+        "
+    )]
+    pub pipe_preview: bool,
+
+    #[arg(
+        long = "max-completion-candidates",
+        help = "Maximum number of paths collected for auto-completion.",
+        long_help = "
+        Stops collecting auto-completion candidates from the input
+        after this many paths, bounding memory and startup time on inputs
+        with a very large number of distinct paths.
+        Completions may be incomplete when the limit is reached.
+        By default, every path is collected.
+        "
+    )]
+    pub max_completion_candidates: Option<usize>,
+
+    #[arg(
+        long = "completion-scope",
+        default_value = "union",
+        value_parser = completion_scope_validator,
+        help = "JSON values to gather completions from ('union', 'intersect' or 'first').",
+        long_help = r#"
+        Controls which of multiple JSON values in the input
+        the auto-completion candidates are gathered from.
+        Acceptable values are "union", "intersect" or "first".
+        - "union" suggests paths found in any of the values.
+        - "intersect" suggests only paths found in every value.
+        - "first" suggests only paths found in the first value.
+        "#,
+    )]
+    pub completion_scope: CompletionScope,
+
+    #[arg(
+        long = "search-mode",
+        env = "JNV_SEARCH_MODE",
+        default_value = "prefix",
+        value_parser = search_mode_validator,
+        help = "How the filter matches completions ('prefix', 'substring' or 'fuzzy').",
+        long_help = r#"
+        Controls how the filter is matched against the auto-completion candidates
+        when pressing Tab. Acceptable values are "prefix", "substring" or "fuzzy".
+        - "prefix" suggests paths starting with the filter.
+        - "substring" suggests paths containing the filter anywhere.
+        - "fuzzy" suggests paths containing the characters of the filter in order,
+          like fzf (e.g. "usrnm" matches ".user.name"), best matches first.
+        "#,
+    )]
+    pub search_mode: SearchMode,
+
+    #[arg(
+        long = "suggestion-sort",
+        env = "JNV_SUGGESTION_SORT",
+        default_value = "relevance",
+        value_parser = suggestion_sort_validator,
+        help = "How completions are ordered ('relevance', 'alphabetical', 'depth' or 'frequency').",
+        long_help = r#"
+        Controls the order of the auto-completion candidates shown when pressing Tab.
+        Acceptable values are "relevance", "alphabetical", "depth" or "frequency".
+        - "relevance" puts the best matches of --search-mode first.
+        - "alphabetical" sorts the paths alphabetically.
+        - "depth" puts shallower paths first (e.g. ".user" before ".user.name").
+        - "frequency" puts the paths picked most often from the suggestions first,
+          with Enter or with Tab when there is only one. The last queries saved
+          with --restore-last-query count as picked once each.
+        Paths that compare equal stay in the order of relevance.
+        "#,
+    )]
+    pub suggestion_sort: SuggestionSort,
+
+    #[arg(
+        long = "jq-backend",
+        env = "JNV_JQ_BACKEND",
+        default_value = "embedded",
+        value_parser = jq_backend_validator,
+        help = "Runs filters with the built-in engine or the jq binary ('embedded' or 'system').",
+        long_help = r#"
+        Selects which implementation of jq runs the filters.
+        Acceptable values are "embedded" or "system".
+        - "embedded" uses jaq, which is built into jnv and needs nothing else.
+        - "system" runs the `jq` binary found on PATH for every filter,
+          so that its version, builtins and modules (e.g. from ~/.jq) are used.
+          This is slower, and fails with an error if jq is not installed.
+        "#,
+    )]
+    pub jq_backend: JqBackend,
+
+    #[arg(
+        long = "input-json",
+        conflicts_with = "input",
+        help = "JSON data to use as the input instead of a file or standard input.",
+        long_help = "
+        Uses the given string as the input data instead of reading a file
+        or standard input, which is handy for trying out filters quickly.
+        The string is validated before the interface starts.
+        "
+    )]
+    pub input_json: Option<String>,
+
+    #[arg(
+        long = "scrollbar",
+        env = "JNV_SCROLLBAR",
+        help = "Draws a scrollbar on the right edge of the JSON viewer.",
+        long_help = "
+        When this option is enabled and the result does not fit in the screen,
+        a scrollbar on the right edge of the JSON viewer shows
+        where the visible lines are within the whole result.
+        "
+    )]
+    pub scrollbar: bool,
+
+    #[arg(
+        long = "tree-guides",
+        env = "JNV_TREE_GUIDES",
+        help = "Draws tree connectors instead of indentation in the JSON viewer.",
+        long_help = "
+        Shows the nesting of the JSON viewer with tree connectors
+        (`├─`, `└─` and `│`), like the output of the `tree` command,
+        instead of indentation alone. Each level is at least 2 columns wide.
+        Folding and moving the cursor work the same either way.
+        Only the display is affected; copied data stays plain JSON.
+        Press Alt+X to switch between the two at runtime.
+        "
+    )]
+    pub tree_guides: bool,
+
+    #[arg(
+        long = "keys-only",
+        env = "JNV_KEYS_ONLY",
+        help = "Shows the types of scalar values instead of the values.",
+        long_help = "
+        Replaces each string, number, boolean and null in the JSON viewer
+        with its type (`str`, `num`, `bool` or `null`), so that only the keys
+        and the structure remain, e.g. to learn the schema of the input.
+        Folding and moving the cursor work as usual.
+        Only the display is affected; copied data keeps the values.
+        Press Alt+G to switch between the two at runtime.
+        "
+    )]
+    pub keys_only: bool,
+
+    #[arg(
+        long = "wrap-values",
+        env = "JNV_WRAP_VALUES",
+        help = "Aligns the wrapped rows of long values with their start.",
+        long_help = "
+        Lines wider than the JSON viewer, such as long strings, are wrapped
+        onto the next rows. By default those rows start at the left edge;
+        with this option they are indented to line up with the start of the value.
+        Press Alt+Z to switch between the two at runtime.
+        "
+    )]
+    pub wrap_values: bool,
+
+    #[arg(
+        short = 'r',
+        long = "raw-output",
+        help = "Shows and writes string results without quotes, like `jq -r`.",
+        long_help = "
+        When a result is a string, it is shown in the JSON viewer and written
+        out (copied, saved, accepted with Ctrl+O or printed with --batch)
+        as its text without quotes or escapes, like `jq -r`.
+        Other results are still formatted as JSON.
+        Press Alt+O to switch it on or off at runtime.
+        "
+    )]
+    pub raw_output: bool,
+
+    #[arg(
+        long = "max-key-width",
+        help = "Maximum display width of keys in the JSON viewer.",
+        long_help = "
+        Shortens keys wider than this number of columns with an ellipsis
+        in the JSON viewer, keeping values of data with verbose keys
+        (e.g. UUIDs) readable. Filters and copied paths use the full keys.
+        "
+    )]
+    pub max_key_width: Option<usize>,
+
+    #[arg(
+        long = "max-value-width",
+        help = "Maximum display width of strings and numbers in the JSON viewer.",
+        long_help = "
+        Shortens strings and numbers wider than this number of columns
+        with an ellipsis in the JSON viewer. Results and copied values are not affected.
+        "
+    )]
+    pub max_value_width: Option<usize>,
+
+    #[arg(
+        long = "ellipsis",
+        value_name = "POSITION",
+        default_value = "end",
+        value_parser = ellipsis_validator,
+        help = "Where to shorten keys and values ('start', 'middle' or 'end').",
+        long_help = r#"
+        Selects which part of keys and values shortened by --max-key-width
+        and --max-value-width is replaced with an ellipsis.
+        Acceptable values are "start", "middle" or "end".
+        - "start" keeps the end, e.g. "…469f".
+        - "middle" keeps both ends, e.g. "0f8…69f", which suits hashes and identifiers.
+        - "end" keeps the start, e.g. "0f8f…".
+        "#,
+    )]
+    pub ellipsis: Ellipsis,
+
+    #[arg(
+        long = "line-numbers",
+        env = "JNV_LINE_NUMBERS",
+        value_name = "MODE",
+        value_parser = line_numbers_validator,
+        help = "Shows line numbers in the JSON viewer ('absolute' or 'relative').",
+        long_help = r#"
+        Shows a gutter with line numbers on the left of the JSON viewer.
+        Acceptable values are "absolute" or "relative".
+        - "absolute" numbers the lines from the first line.
+        - "relative" shows the distance from the line under the cursor,
+          which itself shows its absolute number.
+        Press Alt+N to switch between them and Alt+L to show or hide
+        the line numbers at runtime.
+        "#,
+    )]
+    pub line_numbers: Option<LineNumbers>,
+
+    #[arg(
+        long = "collapse-arrays",
+        help = "Collapses nested arrays in the JSON viewer by default.",
+        long_help = "
+        Shows every array nested in a JSON value collapsed when it is displayed,
+        e.g. to get an overview of objects holding huge arrays.
+        This applies on top of --expand-depth; top-level values stay expanded.
+        "
+    )]
+    pub collapse_arrays: bool,
+
+    #[arg(
+        long = "collapse-objects",
+        help = "Collapses nested objects in the JSON viewer by default.",
+        long_help = "
+        Shows every object nested in a JSON value collapsed when it is displayed.
+        This applies on top of --expand-depth; top-level values stay expanded.
+        "
+    )]
+    pub collapse_objects: bool,
+
+    #[arg(
+        long = "data-uri-mime",
+        default_value = "application/json",
+        help = "MIME type of the data URI copied with Alt+U.",
+        long_help = "
+        Specifies the MIME type used when copying the result
+        to the clipboard as a base64 data URI,
+        e.g. 'text/plain' to have browsers display it as text.
+        "
+    )]
+    pub data_uri_mime: String,
+
+    #[arg(
+        long = "wrap",
+        value_name = "TEMPLATE",
+        value_parser = wrap_template_validator,
+        default_values = ["[{}]", "[{}] | length", "{} | keys"],
+        help = "Templates to wrap the filter in with Alt+1, Alt+2, and so on.",
+        long_help = "
+        Defines templates that the filter can be wrapped in, where `{}`
+        is replaced with the current filter. Specify the option multiple times
+        to define several templates; the first is applied with Alt+1,
+        the second with Alt+2, and so on up to Alt+9.
+        For example, `--wrap '[{}] | length'` turns `.items[]` into `[.items[]] | length`.
+        "
+    )]
+    pub wrap: Vec<String>,
+
+    #[arg(
+        long = "read-only",
+        help = "Starts with the filter editor locked against edits.",
+        long_help = "
+        Starts with the filter editor read-only, so that keystrokes
+        do not modify the filter while the JSON viewer can still be navigated,
+        e.g. for presentations. Press Alt+R to toggle the read-only mode.
+        "
+    )]
+    pub read_only: bool,
+
+    #[arg(
+        long = "newline",
+        default_value = "separate",
+        value_parser = newline_mode_validator,
+        help = "Newlines in copied results ('separate' or 'terminate').",
+        long_help = r#"
+        Controls how the JSON values of a result are joined with newlines
+        when it is copied.
+        Acceptable values are "separate" or "terminate".
+        - "separate" puts newlines between values, without a trailing newline.
+        - "terminate" puts a newline after every value, like jq does.
+        "#,
+    )]
+    pub newline: NewlineMode,
+
+    #[arg(
+        long = "theme-name",
+        env = "JNV_THEME_NAME",
+        value_name = "NAME",
+        default_value = "default",
+        value_parser = theme_name_validator,
+        help = "Color palette to start with ('default', 'light', 'mono', 'monokai', ...).",
+        long_help = r#"
+        Selects the built-in color palette of the filter editor and JSON viewer.
+        Press Alt+T to cycle through the palettes at runtime.
+        Acceptable values are "default", "light", "mono", "monokai",
+        "solarized-dark", "solarized-light" or "nord".
+        - "default" suits terminals with dark backgrounds.
+        - "light" uses darker colors for terminals with light backgrounds.
+        - "mono" uses no colors, only bold, italic and reverse text.
+        - "monokai", "solarized-dark", "solarized-light" and "nord" follow
+          the editor themes of the same names and require a truecolor terminal.
+        Colors given with --color still override the palette.
+        "#,
+    )]
+    pub palette: usize,
+
+    #[arg(
+        long = "stdin-theme-name",
+        env = "JNV_STDIN_THEME_NAME",
+        value_name = "NAME",
+        value_parser = theme_name_validator,
+        help = "Color palette to start with when reading from standard input.",
+        long_help = "
+        Selects a different built-in color palette (see --theme-name)
+        when the input is read from standard input rather than a file,
+        as a visual cue that the session works on piped data.
+        "
+    )]
+    pub stdin_palette: Option<usize>,
+
+    #[arg(
+        long = "color",
+        value_name = "FIELD=COLOR",
+        value_parser = palette::parse_color_override,
+        help = "Overrides a color of the palette, e.g. 'key=#ff8800' (can be repeated).",
+        long_help = "
+        Overrides the color of one style of the palette, on top of --theme-name
+        and of the palettes switched to with Alt+T. Can be given multiple times.
+        FIELD is one of 'prefix', 'active-char' (background), 'bracket-match'
+        (background of the bracket matching the one under the cursor), 'query-pipe',
+        'query-string', 'query-field', 'query-builtin' (the syntax of the filter), 'curly-brackets',
+        'square-brackets', 'key', 'string', 'number', 'boolean', 'null'
+        or 'match' (background of the text found with Ctrl+F).
+        COLOR is a 24-bit hex color such as '#ff8800', for terminals supporting truecolor,
+        or a name such as 'red' or 'dark_grey'.
+        ",
+    )]
+    pub colors: Vec<(String, Color)>,
+
+    #[arg(
+        long = "no-color",
+        help = "Disables colors, also enabled by setting the NO_COLOR environment variable.",
+        long_help = "
+        Writes no color escape codes, neither in the interactive interface
+        nor with --batch, as does setting the NO_COLOR environment variable
+        to a non-empty value (see https://no-color.org). The 'mono' palette
+        is used, so that the cursor and the selected items stay visible
+        through text attributes such as bold and reverse.
+        "
+    )]
+    pub no_color: bool,
+
+    #[arg(
+        long = "screenshot",
+        value_name = "FILE",
+        help = "Writes the initial screen to a file as plain text and exits.",
+        long_help = "
+        Instead of starting the interactive interface, renders the screen
+        it would initially show (with the filter from --query or JNV_QUERY, if any)
+        as plain text into the given file and exits, e.g. to attach to bug reports.
+        The size of the current terminal is used, or 80x24 if there is none.
+        Press Alt+S in the interface to copy the current screen to the clipboard instead.
+        "
+    )]
+    pub screenshot: Option<PathBuf>,
+
+    #[arg(
+        short = 'o',
+        long = "output",
+        value_name = "FILE",
+        help = "Writes the result to a file when quitting with Ctrl+O.",
+        long_help = "
+        Writes the result shown in the JSON viewer to the given file
+        when quitting with Ctrl+O (accept), which exits with status 0.
+        Quitting with Ctrl+C writes nothing and exits with status 1,
+        so that scripts can use jnv as an interactive filter picker.
+        Without this option, Ctrl+O prints the result to standard output instead.
+        "
+    )]
+    pub output: Option<PathBuf>,
+
+    #[arg(
+        long = "dump-paths",
+        help = "Prints the paths used for auto-completion and exits.",
+        long_help = "
+        Instead of starting the interactive interface, prints the paths found
+        in the input data (the auto-completion candidates) to stdout as jq filters,
+        one per line, and exits.
+        Respects --completion-scope, e.g. to feed shell completion or documentation generators.
+        "
+    )]
+    pub dump_paths: bool,
+
+    #[arg(
+        long = "allow-open-url",
+        help = "Allows opening URL values in a web browser with Alt+W.",
+        long_help = "
+        Enables Alt+W, which opens the string value under the cursor
+        in the default web browser if it is an http(s) URL.
+        This is disabled by default, since the input data decides what is opened.
+        "
+    )]
+    pub allow_open_url: bool,
+
+    #[arg(
+        long = "compact-copy",
+        env = "JNV_COMPACT_COPY",
+        help = "Copies results with Alt+Y as compact JSON instead of indented.",
+        long_help = "
+        Makes Alt+Y copy the result as compact single-line JSON,
+        e.g. for pasting into code, instead of indented like the JSON viewer.
+        Press Alt+M to switch between them at runtime.
+        The JSON viewer itself stays indented.
+        "
+    )]
+    pub compact_copy: bool,
+
+    #[arg(
+        long = "clipboard-timeout",
+        env = "JNV_CLIPBOARD_TIMEOUT",
+        value_name = "MILLIS",
+        default_value = "1000",
+        help = "Gives up copying to the clipboard after this many milliseconds.",
+        long_help = "
+        Limits how long copying to the clipboard (Alt+J, Alt+U, Alt+S) may take
+        before an error is shown instead, so that the interface does not freeze
+        when the terminal stops reading, e.g. while waiting for a clipboard manager.
+        "
+    )]
+    pub clipboard_timeout: u64,
+
+    #[arg(
+        long = "keybind-preset",
+        env = "JNV_KEYBIND_PRESET",
+        value_name = "PRESET",
+        default_value = "default",
+        value_parser = keybind_preset_validator,
+        help = "Keybindings to start with ('default', 'emacs' or 'vi').",
+        long_help = r#"
+        Selects the set of keybindings. Acceptable values are "default", "emacs" or "vi".
+        - "default" uses the keys listed in the README, which follow Emacs
+          and readline, so "emacs" is the same.
+        - "vi" starts in insert mode with the default keys; Esc switches to
+          normal mode, where h/l, w/b, 0/$ move in the filter, j/k and gg/G move
+          in the JSON viewer, x, D, dd and u edit the filter, / and n/N search,
+          and i, a, I or A return to insert mode.
+        "#,
+    )]
+    pub keybind_preset: KeybindPreset,
+
+    #[arg(
+        long = "chord-timeout",
+        env = "JNV_CHORD_TIMEOUT",
+        value_name = "MILLIS",
+        default_value = "1000",
+        help = "How long to wait for the second key of a sequence such as 'gg', in milliseconds.",
+        long_help = "
+        With --keybind-preset vi, some keys of normal mode are sequences of two keys:
+        'gg' moves to the first entry of the JSON viewer and 'dd' clears the filter.
+        If the second key is not pressed within this many milliseconds,
+        it starts over as the first key.
+        "
+    )]
+    pub chord_timeout: u64,
+
+    #[arg(
+        long = "debounce",
+        env = "JNV_DEBOUNCE",
+        value_name = "MILLIS",
+        default_value = "0",
+        help = "Waits this many milliseconds after typing stops before running the filter.",
+        long_help = "
+        Instead of running the filter on every keystroke, waits until no edit
+        was made for this many milliseconds, which keeps typing responsive
+        when filters over large inputs are slow. 0 runs the filter immediately.
+        Press Alt+= and Alt+- to change it by 100ms at runtime.
+        "
+    )]
+    pub debounce: u64,
+
+    #[arg(
+        long = "elapsed-threshold",
+        value_name = "MILLIS",
+        default_value = "1000",
+        help = "Shows how long a filter has been running once it takes at least this many milliseconds.",
+        long_help = "
+        Once a filter has been running for this many milliseconds,
+        the hint message shows how many seconds it has been running,
+        updated until it finishes, so that a slow filter can be told apart
+        from a hung one. When it succeeds, the hint shows how long it took.
+        "
+    )]
+    pub elapsed_threshold: u64,
+
+    #[arg(
+        long = "max-fps",
+        env = "JNV_MAX_FPS",
+        value_name = "FPS",
+        default_value = "60",
+        help = "Redraws the screen at most this many times per second (0 for no limit).",
+        long_help = "
+        Caps how often the screen is redrawn. Events arriving faster than that,
+        e.g. while holding down a key, are applied together and drawn once,
+        which reduces flicker and CPU use on slow terminals.
+        Set to 0 to redraw after every event.
+        "
+    )]
+    pub max_fps: u32,
+
+    #[arg(
+        short = 'Q',
+        long = "quiet",
+        conflicts_with = "verbose",
+        help = "Suppresses informational messages on stderr.",
+        long_help = "
+        Suppresses informational messages printed to stderr
+        (e.g. the members loaded from a tar archive), leaving only errors.
+        "
+    )]
+    pub quiet: bool,
+
+    #[arg(
+        long = "verbose",
+        help = "Prints additional informational messages on stderr.",
+        long_help = "
+        Prints additional informational messages to stderr,
+        such as the number of JSON values loaded from the input.
+        "
+    )]
+    pub verbose: bool,
 }
 
 fn edit_mode_validator(val: &str) -> Result<text_editor::Mode> {
@@ -133,73 +1096,644 @@ fn edit_mode_validator(val: &str) -> Result<text_editor::Mode> {
     }
 }
 
+fn wrap_template_validator(val: &str) -> Result<String> {
+    if val.contains("{}") {
+        Ok(val.to_string())
+    } else {
+        Err(anyhow!("wrap template must contain the '{{}}' placeholder"))
+    }
+}
+
+fn suggestion_placement_validator(val: &str) -> Result<SuggestionPlacement> {
+    match val {
+        "above" => Ok(SuggestionPlacement::Above),
+        "below" | "" => Ok(SuggestionPlacement::Below),
+        _ => Err(anyhow!("suggestion-placement must be 'above' or 'below'")),
+    }
+}
+
+fn theme_name_validator(val: &str) -> Result<usize> {
+    let palettes = palette::palettes();
+    palettes
+        .iter()
+        .position(|palette| palette.name == val)
+        .ok_or_else(|| {
+            anyhow!(
+                "theme-name must be one of {}",
+                palettes
+                    .iter()
+                    .map(|palette| format!("'{}'", palette.name))
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            )
+        })
+}
+
+fn ellipsis_validator(val: &str) -> Result<Ellipsis> {
+    match val {
+        "start" => Ok(Ellipsis::Start),
+        "middle" => Ok(Ellipsis::Middle),
+        "end" => Ok(Ellipsis::End),
+        _ => Err(anyhow!("ellipsis must be 'start', 'middle' or 'end'")),
+    }
+}
+
+fn indent_char_validator(val: &str) -> Result<char> {
+    match val {
+        "space" => Ok(' '),
+        "tab" => Ok('\t'),
+        _ => Err(anyhow!("indent-char must be 'space' or 'tab'")),
+    }
+}
+
+fn line_numbers_validator(val: &str) -> Result<LineNumbers> {
+    match val {
+        "absolute" => Ok(LineNumbers::Absolute),
+        "relative" => Ok(LineNumbers::Relative),
+        _ => Err(anyhow!("line-numbers must be 'absolute' or 'relative'")),
+    }
+}
+
+fn jq_backend_validator(val: &str) -> Result<JqBackend> {
+    match val {
+        "embedded" => Ok(JqBackend::Embedded),
+        "system" => Ok(JqBackend::System),
+        _ => Err(anyhow!("jq-backend must be 'embedded' or 'system'")),
+    }
+}
+
+fn search_mode_validator(val: &str) -> Result<SearchMode> {
+    match val {
+        "prefix" => Ok(SearchMode::Prefix),
+        "substring" => Ok(SearchMode::Substring),
+        "fuzzy" => Ok(SearchMode::Fuzzy),
+        _ => Err(anyhow!(
+            "search-mode must be 'prefix', 'substring' or 'fuzzy'"
+        )),
+    }
+}
+
+fn keybind_preset_validator(val: &str) -> Result<KeybindPreset> {
+    match val {
+        "default" | "emacs" => Ok(KeybindPreset::Default),
+        "vi" => Ok(KeybindPreset::Vi),
+        _ => Err(anyhow!("keybind-preset must be 'default', 'emacs' or 'vi'")),
+    }
+}
+
+fn suggestion_sort_validator(val: &str) -> Result<SuggestionSort> {
+    match val {
+        "relevance" => Ok(SuggestionSort::Relevance),
+        "alphabetical" => Ok(SuggestionSort::Alphabetical),
+        "depth" => Ok(SuggestionSort::Depth),
+        "frequency" => Ok(SuggestionSort::Frequency),
+        _ => Err(anyhow!(
+            "suggestion-sort must be 'relevance', 'alphabetical', 'depth' or 'frequency'"
+        )),
+    }
+}
+
+fn completion_scope_validator(val: &str) -> Result<CompletionScope> {
+    match val {
+        "union" | "" => Ok(CompletionScope::Union),
+        "intersect" => Ok(CompletionScope::Intersect),
+        "first" => Ok(CompletionScope::First),
+        _ => Err(anyhow!(
+            "completion-scope must be 'union', 'intersect' or 'first'"
+        )),
+    }
+}
+
+fn newline_mode_validator(val: &str) -> Result<NewlineMode> {
+    match val {
+        "separate" | "" => Ok(NewlineMode::Separate),
+        "terminate" => Ok(NewlineMode::Terminate),
+        _ => Err(anyhow!("newline must be 'separate' or 'terminate'")),
+    }
+}
+
+/// How the input data is parsed.
+#[derive(Clone, Copy)]
+pub enum InputFormat {
+    /// One or more JSON values.
+    Json,
+    /// Comma-separated values with a header row, one JSON object per row.
+    Csv,
+}
+
+fn input_format_validator(val: &str) -> Result<InputFormat> {
+    match val {
+        "json" => Ok(InputFormat::Json),
+        "csv" => Ok(InputFormat::Csv),
+        _ => Err(anyhow!("input-format must be 'json' or 'csv'")),
+    }
+}
+
+fn byte_size_validator(val: &str) -> Result<usize> {
+    let (number, unit) = match val.char_indices().last() {
+        Some((i, 'K' | 'k')) => (&val[..i], 1 << 10),
+        Some((i, 'M' | 'm')) => (&val[..i], 1 << 20),
+        Some((i, 'G' | 'g')) => (&val[..i], 1 << 30),
+        _ => (val, 1),
+    };
+    number
+        .parse::<usize>()
+        .ok()
+        .and_then(|number| number.checked_mul(unit))
+        .ok_or_else(|| anyhow!("max-bytes must be a number of bytes, e.g. 1048576, 512K or 1G"))
+}
+
 /// Parses the input based on the provided arguments.
 ///
-/// This function reads input data from either a specified file or standard input.
-/// If the `input` argument is `None`, or if it is a path
-/// that equals "-", data is read from standard input.
+/// This function reads input data from either the specified files or standard input,
+/// unless the data is given directly with `input_json`.
+/// If no `input` argument is given, or for a path that equals "-",
+/// data is read from standard input.
 /// Otherwise, the function attempts to open and
-/// read from the file specified in the `input` argument.
-fn parse_input(args: &Args) -> Result<String> {
-    let mut ret = String::new();
-
-    match &args.input {
-        None => {
-            io::stdin().read_to_string(&mut ret)?;
-        }
-        Some(path) => {
-            if path == &PathBuf::from("-") {
-                io::stdin().read_to_string(&mut ret)?;
-            } else {
-                File::open(path)?.read_to_string(&mut ret)?;
-            }
+/// read from each file specified in the `input` argument, in order.
+/// Returns the contents of each source along with its name,
+/// and whether `max_bytes` cut the input short, which is also told in `notes`
+/// along with other messages about what was read.
+fn parse_input(args: &Args, notes: &mut Vec<String>) -> Result<(Vec<(String, String)>, bool)> {
+    if let Some(json) = &args.input_json {
+        return Ok((vec![("--input-json".to_string(), json.clone())], false));
+    }
+
+    let stdin = PathBuf::from("-");
+    let paths = match args.input.as_slice() {
+        [] => std::slice::from_ref(&stdin),
+        paths => paths,
+    };
+    let mut ret = Vec::new();
+    let mut remaining = args.max_bytes;
+    for path in paths {
+        let name = if path == &stdin {
+            "standard input".to_string()
+        } else {
+            path.display().to_string()
+        };
+        let (contents, limited) = parse_file(args, path, remaining, notes)?;
+        remaining = remaining.map(|remaining| remaining.saturating_sub(contents.len()));
+        ret.push((name, contents));
+        if limited {
+            notes.push(format!(
+                "Stopped reading the input at {} after {} bytes (see --max-bytes)",
+                ret.last()
+                    .map(|(name, _)| name.as_str())
+                    .unwrap_or_default(),
+                args.max_bytes.unwrap_or_default()
+            ));
+            return Ok((ret, true));
         }
     }
+    Ok((ret, false))
+}
 
-    Ok(ret)
+/// Reads the file at `path`, or standard input if it equals "-".
+/// If `no_follow_symlinks` is set, the file must not be a symbolic link.
+/// Gzip-compressed data is decompressed first.
+/// If the data is a tar or zip archive, the contents of the members matching
+/// `archive_glob` are concatenated instead.
+/// At most `max_bytes` are read; the data is then cut back with [`cut_at_boundary`],
+/// which is told by returning `true` along with it.
+/// The members loaded from an archive are listed in `notes`.
+fn parse_file(
+    args: &Args,
+    path: &PathBuf,
+    max_bytes: Option<usize>,
+    notes: &mut Vec<String>,
+) -> Result<(String, bool)> {
+    let mut ret = Vec::new();
+    // One byte more than allowed tells whether there is more to read.
+    let limit = max_bytes.map_or(u64::MAX, |max_bytes| max_bytes as u64 + 1);
+
+    if path == &PathBuf::from("-") {
+        io::stdin().take(limit).read_to_end(&mut ret)?;
+    } else {
+        if args.no_follow_symlinks && fs::symlink_metadata(path)?.is_symlink() {
+            return Err(anyhow!(
+                "{} is a symbolic link, which is not followed with --no-follow-symlinks",
+                path.display()
+            ));
+        }
+        File::open(path)?.take(limit).read_to_end(&mut ret)?;
+    }
+
+    if let Some(max_bytes) = max_bytes.filter(|&max_bytes| ret.len() > max_bytes) {
+        if gzip::is_gzip(&ret) || archive::is_tar(&ret) || archive::is_zip(&ret) {
+            return Err(anyhow!(
+                "{} is compressed or archived and larger than --max-bytes, so it cannot be cut",
+                path.display()
+            ));
+        }
+        ret.truncate(max_bytes);
+        // The cut may fall in the middle of a character, whose start is kept out.
+        let valid = match std::str::from_utf8(&ret) {
+            Ok(text) => text,
+            Err(e) if e.error_len().is_none() => std::str::from_utf8(&ret[..e.valid_up_to()])?,
+            Err(_) => return Err(anyhow!("Input is not valid UTF-8")),
+        };
+        let line_based = args.ndjson || matches!(args.input_format, InputFormat::Csv);
+        return Ok((cut_at_boundary(valid, line_based).to_string(), true));
+    }
+
+    if gzip::is_gzip(&ret) {
+        ret = gzip::decompress(ret)?;
+    }
+
+    let archive = if archive::is_tar(&ret) {
+        Some(("tar", archive::read_tar(&ret, &args.archive_glob)?))
+    } else if archive::is_zip(&ret) {
+        Some(("zip", archive::read_zip(&ret, &args.archive_glob)?))
+    } else {
+        None
+    };
+    if let Some((kind, members)) = archive {
+        if members.is_empty() {
+            return Err(anyhow!(
+                "No member of the {} archive matches '{}'",
+                kind,
+                args.archive_glob
+            ));
+        }
+        notes.push(format!(
+            "Loaded {} member(s) from the {} archive: {}",
+            members.len(),
+            kind,
+            members
+                .iter()
+                .map(|(name, _)| name.as_str())
+                .collect::<Vec<_>>()
+                .join(", ")
+        ));
+        return Ok((
+            members
+                .into_iter()
+                .map(|(_, contents)| contents)
+                .collect::<Vec<_>>()
+                .join("\n"),
+            false,
+        ));
+    }
+
+    String::from_utf8(ret)
+        .map(|text| (text, false))
+        .map_err(|_| anyhow!("Input is not valid UTF-8"))
+}
+
+/// Cuts `text`, which ends at an arbitrary point, back to the end of its last complete line
+/// if `line_based`, or else of its last complete JSON value.
+fn cut_at_boundary(text: &str, line_based: bool) -> &str {
+    if line_based {
+        return text.rfind('\n').map_or("", |i| &text[..=i]);
+    }
+    let mut values = Deserializer::from_str(text).into_iter::<serde::de::IgnoredAny>();
+    let mut end = 0;
+    while let Some(Ok(_)) = values.next() {
+        end = values.byte_offset();
+    }
+    &text[..end]
 }
 
 /// Deserializes a JSON string into a vector of `serde_json::Value`.
 ///
+/// The string may hold several JSON values concatenated one after another,
+/// compact or pretty-printed across multiple lines, separated by whitespace
+/// or by nothing at all when the values delimit themselves (e.g. `{}{}`).
+///
 /// This function takes a JSON string as input and attempts to parse it into a vector
 /// of `serde_json::Value`, which represents any valid JSON value (e.g., object, array, string, number).
 /// It leverages `serde_json::Deserializer` to parse the string and collect the results.
 ///
 /// # Arguments
 /// * `json_str` - A string slice that holds the JSON data to be deserialized.
+/// * `limit_length` - The maximum number of JSON values to deserialize.
+/// * `max_depth` - The maximum nesting depth of arrays and objects in each JSON value.
 ///
 /// # Returns
-/// An `anyhow::Result` wrapping a vector of `serde_json::Value`. On success, it contains the parsed
-/// JSON data. On failure, it contains an error detailing what went wrong during parsing.
+/// An `anyhow::Result` wrapping a vector of `serde_json::Value` and whether `limit_length`
+/// left values out. On success, it contains the parsed JSON data.
+/// On failure, it contains an error detailing what went wrong during parsing.
 fn deserialize_json(
     json_str: &str,
     limit_length: Option<usize>,
-) -> anyhow::Result<Vec<serde_json::Value>> {
-    let deserializer = Deserializer::from_str(json_str).into_iter::<serde_json::Value>();
-    let results = match limit_length {
-        Some(l) => deserializer.take(l).collect::<Result<Vec<_>, _>>(),
-        None => deserializer.collect::<Result<Vec<_>, _>>(),
+    max_depth: usize,
+) -> anyhow::Result<(Vec<serde_json::Value>, bool)> {
+    let mut deserializer = Deserializer::from_str(json_str);
+    // The depth is limited while parsing instead, which may allow more than serde_json's 128.
+    deserializer.disable_recursion_limit();
+    let mut deserializer = deserializer.into_iter::<depth::Limited>();
+    let results = depth::with_max_depth(max_depth, || {
+        deserializer
+            .by_ref()
+            .take(limit_length.unwrap_or(usize::MAX))
+            .map(|result| result.map(|depth::Limited(value)| value))
+            .collect::<Result<Vec<_>, _>>()
+    })?;
+    // Whatever follows the last value kept is not parsed, only checked for being there.
+    let truncated = !json_str[deserializer.byte_offset()..].trim().is_empty();
+    Ok((results, truncated))
+}
+
+/// Deserializes each non-blank line of `json_str` as a separate JSON value,
+/// keeping at most `limit_length` of them, and tells whether that left lines out.
+/// A line that fails to parse is replaced by an object describing the error.
+fn deserialize_ndjson(
+    json_str: &str,
+    limit_length: Option<usize>,
+    max_depth: usize,
+) -> (Vec<serde_json::Value>, bool) {
+    let mut lines = json_str
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty());
+    let values = lines
+        .by_ref()
+        .take(limit_length.unwrap_or(usize::MAX))
+        .map(|(i, line)| {
+            depth::from_str(line, max_depth).unwrap_or_else(|e| {
+                serde_json::json!({
+                    "error": e.to_string(),
+                    "line": i + 1,
+                    "text": line,
+                })
+            })
+        })
+        .collect();
+    (values, lines.next().is_some())
+}
+
+/// Collects the variables bound with `--arg` and `--argjson`.
+fn jq_vars(args: &Args) -> Result<Vec<(String, serde_json::Value)>> {
+    let mut ret = args
+        .arg
+        .chunks(2)
+        .map(|pair| (pair[0].clone(), serde_json::Value::String(pair[1].clone())))
+        .collect::<Vec<_>>();
+    for pair in args.argjson.chunks(2) {
+        let value = serde_json::from_str(&pair[1])
+            .map_err(|e| anyhow!("Invalid JSON for --argjson {}: {}", pair[0], e))?;
+        ret.push((pair[0].clone(), value));
+    }
+    Ok(ret)
+}
+
+/// Reads the definitions of `--library`, empty if it is not given,
+/// and checks that they parse by running them once before the identity filter.
+fn jq_library(args: &Args, vars: &[(String, serde_json::Value)]) -> Result<String> {
+    let Some(path) = &args.library else {
+        return Ok(String::new());
     };
-    results.map_err(anyhow::Error::from)
+    let library = fs::read_to_string(path)
+        .map_err(|e| anyhow!("Failed to read --library {}: {}", path.display(), e))?;
+    jnv::run_query(".", &[], vars, &library, args.jq_backend)
+        .map_err(|e| anyhow!("Invalid jq library {}: {}", path.display(), e))?;
+    Ok(library)
+}
+
+/// Treats standard output being closed early (e.g. when piped into `head`) as success.
+fn ignore_broken_pipe(result: io::Result<()>) -> Result<()> {
+    match result {
+        Err(e) if e.kind() == io::ErrorKind::BrokenPipe => Ok(()),
+        result => Ok(result?),
+    }
+}
+
+/// Resolves the filter that the editor starts with.
+///
+/// The filter is taken from `--query`, `--query-file` or `--seed-query`, or else
+/// from the `JNV_QUERY` environment variable when it is set to a non-empty value.
+/// Otherwise the editor starts empty.
+fn initial_query(args: &Args) -> Result<Option<String>> {
+    if let Some(path) = &args.query_file {
+        let text = fs::read_to_string(path)
+            .map_err(|e| anyhow!("Failed to read --query-file {}: {}", path.display(), e))?;
+        return Ok(Some(strip_comment_lines(&text)));
+    }
+    Ok(args
+        .query
+        .clone()
+        .or_else(|| args.seed_query.clone())
+        .or_else(|| env::var("JNV_QUERY").ok().filter(|query| !query.is_empty())))
+}
+
+/// Leaves out the lines of the jq filter `text` that are comments, i.e. start with `#`,
+/// and trailing whitespace. Comments after code on the same line are kept,
+/// since telling them apart from `#` in strings takes parsing the filter.
+fn strip_comment_lines(text: &str) -> String {
+    text.lines()
+        .filter(|line| !line.trim_start().starts_with('#'))
+        .collect::<Vec<_>>()
+        .join("\n")
+        .trim_end()
+        .to_string()
+}
+
+/// Reads and deserializes the input data as specified by the arguments.
+/// The values of all input files are concatenated, keeping at most
+/// `json_limit_length` of them in total, and wrapped in an array with `--slurp`.
+/// Also returns the number of bytes read and whether the limit left values out.
+/// Messages about what was read are added to `notes`, for the caller to show.
+fn load_input(
+    args: &Args,
+    notes: &mut Vec<String>,
+) -> Result<(Vec<serde_json::Value>, InputSources, usize, bool)> {
+    let (inputs, limited) = parse_input(args, notes)?;
+    let multiple = inputs.len() > 1;
+    let bytes = inputs.iter().map(|(_, input)| input.len()).sum();
+    let mut values = Vec::new();
+    let mut sources = Vec::new();
+    let mut truncated = limited;
+    for (name, input) in inputs {
+        let limit_length = args
+            .json_limit_length
+            .map(|limit| limit.saturating_sub(values.len()));
+        let (loaded, rest) = if args.ndjson {
+            deserialize_ndjson(&input, limit_length, args.max_depth)
+        } else if let InputFormat::Csv = args.input_format {
+            csv::to_json(&input, limit_length).map_err(|e| {
+                if multiple {
+                    anyhow!("{}: {}", name, e)
+                } else {
+                    e
+                }
+            })?
+        } else {
+            deserialize_json(&input, limit_length, args.max_depth).map_err(|e| {
+                if args.input_json.is_some() {
+                    anyhow!("Invalid JSON in --input-json: {}", e)
+                } else if multiple {
+                    anyhow!("{}: {}", name, e)
+                } else {
+                    e
+                }
+            })?
+        };
+
+        if args.verbose {
+            eprintln!(
+                "Loaded {} JSON value(s) ({} bytes) from {}",
+                loaded.len(),
+                input.len(),
+                name
+            );
+        }
+        if args.verbose && rest {
+            eprintln!(
+                "Skipped the rest of {} after {} JSON value(s) in total (see --limit-length)",
+                name,
+                values.len() + loaded.len()
+            );
+        }
+        truncated |= rest;
+        sources.push((name, loaded.len()));
+        values.extend(loaded);
+    }
+    if args.slurp {
+        let names = sources
+            .into_iter()
+            .map(|(name, _)| name)
+            .collect::<Vec<_>>()
+            .join(", ");
+        return Ok((
+            vec![serde_json::Value::Array(values)],
+            vec![(names, 1)],
+            bytes,
+            truncated,
+        ));
+    }
+    Ok((values, sources, bytes, truncated))
+}
+
+/// Starts reading JSON values from standard input in the background for `--follow`
+/// and waits for the first one.
+/// Returns the values read so far and a receiver for the ones that follow.
+fn follow_stdin(
+    args: &Args,
+) -> Result<(Vec<serde_json::Value>, mpsc::Receiver<serde_json::Value>)> {
+    if args.input.iter().any(|path| path != &PathBuf::from("-")) {
+        return Err(anyhow!("--follow only reads standard input"));
+    }
+
+    let (tx, rx) = mpsc::channel();
+    let limit_length = args.json_limit_length.unwrap_or(usize::MAX);
+    let max_depth = args.max_depth;
+    let max_bytes = args.max_bytes;
+    thread::spawn(move || {
+        let stdin = io::stdin().take(max_bytes.map_or(u64::MAX, |max_bytes| max_bytes as u64));
+        depth::with_max_depth(max_depth, || {
+            // There is no telling where the next value starts after a malformed
+            // or too deeply nested one.
+            let values = depth::from_reader(stdin).map_while(Result::ok);
+            for depth::Limited(value) in values.take(limit_length) {
+                if tx.send(value).is_err() {
+                    break;
+                }
+            }
+        });
+    });
+
+    let first = rx
+        .recv()
+        .map_err(|_| anyhow!("No JSON value was read from standard input"))?;
+    Ok((iter::once(first).chain(rx.try_iter()).collect(), rx))
 }
 
 fn main() -> Result<()> {
     let args = Args::parse();
 
-    let input = parse_input(&args)?;
-    let input_stream = deserialize_json(&input, args.json_limit_length)?;
+    let started = Instant::now();
+    let (input_stream, sources, follow, bytes, truncated) = if args.follow {
+        let (input_stream, follow) = follow_stdin(&args)?;
+        (input_stream, Vec::new(), Some(follow), None, false)
+    } else {
+        let mut notes = Vec::new();
+        let (input_stream, sources, bytes, truncated) = load_input(&args, &mut notes)?;
+        if !args.quiet {
+            notes.iter().for_each(|note| eprintln!("{}", note));
+        }
+        (input_stream, sources, None, Some(bytes), truncated)
+    };
+    // The size is unknown with --follow, which keeps reading.
+    let stats = match bytes {
+        Some(bytes) if args.stats => Some(format!(
+            "{} bytes, {} value(s) parsed in {}ms",
+            bytes,
+            input_stream.len(),
+            started.elapsed().as_millis()
+        )),
+        _ => None,
+    };
+    if let (Some(stats), true) = (&stats, args.batch) {
+        eprintln!("{}", stats);
+    }
+    let vars = jq_vars(&args)?;
+    let library = jq_library(&args, &vars)?;
+    if args.dump_paths {
+        let mut stdout = io::stdout().lock();
+        return ignore_broken_pipe(
+            completion_paths(&input_stream, args.completion_scope)
+                .try_for_each(|path| writeln!(stdout, "{}", path)),
+        );
+    }
+    let input_loader =
+        if !args.input.is_empty() && args.input.iter().all(|path| path != &PathBuf::from("-")) {
+            // What --verbose prints is only for startup, before the terminal is in raw mode.
+            let args = Args {
+                verbose: false,
+                ..args.clone()
+            };
+            Some(Box::new(move || {
+                let mut notes = Vec::new();
+                load_input(&args, &mut notes)
+                    .map(|(values, sources, _, truncated)| (values, sources, truncated, notes))
+            }) as InputLoader)
+        } else {
+            None
+        };
+
+    // The input file whose last query is restored and saved with --restore-last-query.
+    let query_key = match args.input.as_slice() {
+        [path] if args.restore_last_query && path != &PathBuf::from("-") => Some(path),
+        _ => None,
+    };
+
+    let query = initial_query(&args)?;
+    let mut texteditor = TextEditor::default();
+    if let Some(query) = query
+        .clone()
+        .or_else(|| query_key.and_then(|path| state::load_last_query(path)))
+    {
+        texteditor.replace(&query);
+    }
+
+    let reads_stdin = args.input_json.is_none()
+        && (args.input.is_empty() || args.input.iter().any(|path| path == &PathBuf::from("-")));
+    let no_color = args.no_color || env::var("NO_COLOR").is_ok_and(|val| !val.is_empty());
+    if no_color {
+        // Strips the colors from every style written to the terminal.
+        style::force_color_output(false);
+    }
+    let palette_index = match args.stdin_palette {
+        _ if no_color => theme_name_validator("mono")?,
+        Some(index) if reads_stdin => index,
+        _ => args.palette,
+    };
+    let palette = palette::palettes()
+        .swap_remove(palette_index)
+        .with_colors(&args.colors);
 
     let filter_editor = text_editor::State {
-        texteditor: Default::default(),
+        texteditor,
         history: Default::default(),
         prefix: String::from("❯❯ "),
         mask: Default::default(),
-        prefix_style: StyleBuilder::new().fgc(Color::Blue).build(),
-        active_char_style: StyleBuilder::new().bgc(Color::Magenta).build(),
+        prefix_style: palette.prefix_style,
+        active_char_style: palette.active_char_style,
         inactive_char_style: StyleBuilder::new().build(),
         edit_mode: args.edit_mode,
         word_break_chars: HashSet::from(['.', '|', '(', ')', '[', ']']),
-        lines: Default::default(),
+        lines: args.max_filter_lines,
     };
 
     let hint_message = text::State {
@@ -210,46 +1744,337 @@ fn main() -> Result<()> {
             .build(),
     };
 
+    let pane_separator = args.pane_separator.map(|ch| text::State {
+        text: ch.to_string(),
+        style: StyleBuilder::new().fgc(Color::DarkGrey).build(),
+    });
+
     let suggestions = listbox::State {
         listbox: listbox::Listbox::from_iter(Vec::<String>::new()),
         cursor: String::from("❯ "),
-        active_item_style: Some(
+        active_item_style: Some(if no_color {
+            StyleBuilder::new()
+                .attrs(Attributes::from(Attribute::Reverse))
+                .build()
+        } else {
             StyleBuilder::new()
                 .fgc(Color::Grey)
                 .bgc(Color::Yellow)
-                .build(),
-        ),
+                .build()
+        }),
         inactive_item_style: Some(StyleBuilder::new().fgc(Color::Grey).build()),
         lines: Some(args.suggestion_list_length),
     };
 
     let json_theme = JsonTheme {
-        curly_brackets_style: StyleBuilder::new()
-            .attrs(Attributes::from(Attribute::Bold))
-            .build(),
-        square_brackets_style: StyleBuilder::new()
-            .attrs(Attributes::from(Attribute::Bold))
-            .build(),
-        key_style: StyleBuilder::new().fgc(Color::Cyan).build(),
-        string_value_style: StyleBuilder::new().fgc(Color::Green).build(),
-        number_value_style: StyleBuilder::new().build(),
-        boolean_value_style: StyleBuilder::new().build(),
-        null_value_style: StyleBuilder::new().fgc(Color::Grey).build(),
+        curly_brackets_style: palette.curly_brackets_style,
+        square_brackets_style: palette.square_brackets_style,
+        key_style: palette.key_style,
+        string_value_style: palette.string_value_style,
+        number_value_style: palette.number_value_style,
+        boolean_value_style: palette.boolean_value_style,
+        null_value_style: palette.null_value_style,
+        match_style: palette.match_style,
         active_item_attribute: Attribute::Bold,
         inactive_item_attribute: Attribute::Dim,
         lines: Default::default(),
         indent: args.indent,
+        indent_char: args.indent_char,
+        number_separator: args.number_separator,
+        scrollbar: args.scrollbar,
+        max_key_width: args.max_key_width,
+        max_value_width: args.max_value_width,
+        ellipsis: args.ellipsis,
+        line_numbers: args.line_numbers,
+        line_number_style: StyleBuilder::new().fgc(Color::DarkGrey).build(),
+        tree_guides: args.tree_guides,
+        tree_guide_style: StyleBuilder::new().fgc(Color::DarkGrey).build(),
+        wrap_values: args.wrap_values,
+        raw_output: args.raw_output,
+        keys_only: args.keys_only,
     };
 
+    if args.stream_output {
+        let indent = json_theme.indent_unit();
+        let styled = io::stdout().is_terminal() && !no_color;
+        let mut stdout = io::stdout().lock();
+        let ret = jnv::stream_query(
+            query.as_deref().unwrap_or("."),
+            &input_stream,
+            &vars,
+            &library,
+            args.jq_backend,
+            |value| {
+                if styled {
+                    jnv::format_styled(vec![value], json_theme.clone())
+                        .iter()
+                        .try_for_each(|line| writeln!(stdout, "{}", line.styled_display()))?;
+                } else {
+                    writeln!(
+                        stdout,
+                        "{}",
+                        jnv::to_output_string(&value, Some(&indent), args.raw_output)
+                    )?;
+                }
+                stdout.flush()
+            },
+        );
+        return match ret.map_err(|e| e.downcast::<io::Error>()) {
+            Ok(()) => Ok(()),
+            Err(Ok(e)) => ignore_broken_pipe(Err(e)),
+            Err(Err(e)) => Err(anyhow!("Failed to execute jq query: {}", e)),
+        };
+    }
+    if args.batch {
+        let results = jnv::run_query(
+            query.as_deref().unwrap_or("."),
+            &input_stream,
+            &vars,
+            &library,
+            args.jq_backend,
+        )
+        .map_err(|e| anyhow!("Failed to execute jq query: {}", e))?;
+        let indent = json_theme.indent_unit();
+        let mut stdout = io::stdout().lock();
+        return ignore_broken_pipe(if stdout.is_terminal() && !no_color {
+            jnv::format_styled(results, json_theme)
+                .iter()
+                .try_for_each(|line| writeln!(stdout, "{}", line.styled_display()))
+        } else {
+            results.iter().try_for_each(|value| {
+                writeln!(
+                    stdout,
+                    "{}",
+                    jnv::to_output_string(value, Some(&indent), args.raw_output)
+                )
+            })
+        });
+    }
+
     let mut prompt = Jnv::try_new(
         input_stream,
         filter_editor,
         hint_message,
         suggestions,
         json_theme,
-        args.json_expand_depth,
-        args.no_hint,
+        jnv::Options {
+            json_expand_depth: args.json_expand_depth,
+            no_hint: args.no_hint,
+            input_loader,
+            pane_separator,
+            suggestion_placement: args.suggestion_placement,
+            show_keys: args.show_keys,
+            status_bar: args.status_bar || stats.is_some(),
+            pipe_preview: args.pipe_preview,
+            max_completion_candidates: args.max_completion_candidates,
+            input_paths: args
+                .input
+                .iter()
+                .filter(|&path| path != &PathBuf::from("-"))
+                .cloned()
+                .collect(),
+            slurp: args.slurp,
+            input_sources: sources,
+            input_truncated: truncated,
+            collapse_arrays: args.collapse_arrays,
+            collapse_objects: args.collapse_objects,
+            data_uri_mime: args.data_uri_mime.clone(),
+            wrap_templates: args.wrap.clone(),
+            read_only: args.read_only,
+            newline_mode: args.newline,
+            palette: palette_index,
+            completion_scope: args.completion_scope,
+            allow_open_url: args.allow_open_url,
+            compact_copy: args.compact_copy,
+            clipboard_timeout: Duration::from_millis(args.clipboard_timeout),
+            colors: args.colors.clone(),
+            vars,
+            library,
+            search_mode: args.search_mode,
+            suggestion_sort: args.suggestion_sort,
+            follow,
+            jq_backend: args.jq_backend,
+            debounce: Duration::from_millis(args.debounce),
+            keybind_preset: args.keybind_preset,
+            chord_timeout: Duration::from_millis(args.chord_timeout),
+            elapsed_threshold: Duration::from_millis(args.elapsed_threshold),
+            persist_pins: args.persist_pins,
+            max_result_lines: args.max_result_lines,
+            stats,
+        },
     )?;
-    let _ = prompt.run()?;
+    if let Some(path) = &args.screenshot {
+        let (width, height) = terminal::size().unwrap_or((80, 24));
+        fs::write(path, prompt.renderer.screenshot(width, height))?;
+        return Ok(());
+    }
+    let _ = jnv::run(&mut prompt, args.max_fps, args.mouse)?;
+    let result = prompt.renderer.accepted_result();
+    let last_query = prompt.renderer.last_successful_query().map(String::from);
+    // Restore the terminal before writing out the result.
+    drop(prompt);
+
+    if let (Some(path), Some(query)) = (query_key, last_query) {
+        if let Err(e) = state::save_last_query(path, &query) {
+            eprintln!("Failed to save the last query: {}", e);
+        }
+    }
+
+    match (result, &args.output) {
+        (Some(result), Some(path)) => fs::write(path, result)?,
+        (Some(result), None) => {
+            let mut stdout = io::stdout().lock();
+            write!(stdout, "{}", result)?;
+            if !result.ends_with('\n') {
+                writeln!(stdout)?;
+            }
+        }
+        (None, Some(_)) => return Err(anyhow!("Quit without accepting a result (Ctrl+O)")),
+        (None, None) => {}
+    }
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    mod byte_size_validator {
+        use super::*;
+
+        #[test]
+        fn test() {
+            assert_eq!(1000, byte_size_validator("1000").unwrap());
+            assert_eq!(512 << 10, byte_size_validator("512K").unwrap());
+            assert_eq!(2 << 30, byte_size_validator("2g").unwrap());
+            assert!(byte_size_validator("1T").is_err());
+            assert!(byte_size_validator("M").is_err());
+        }
+    }
+
+    mod cut_at_boundary {
+        use super::*;
+
+        #[test]
+        fn test_json() {
+            assert_eq!(
+                "{\"a\": 1}\n[2]",
+                cut_at_boundary("{\"a\": 1}\n[2]\n{\"b\"", false)
+            );
+            assert_eq!("", cut_at_boundary("[1, 2", false));
+        }
+
+        #[test]
+        fn test_lines() {
+            assert_eq!("a,b\n1,2\n", cut_at_boundary("a,b\n1,2\n3", true));
+            assert_eq!("", cut_at_boundary("a,b", true));
+        }
+    }
+
+    mod strip_comment_lines {
+        use super::*;
+
+        #[test]
+        fn test() {
+            assert_eq!(
+                ".items[]\n| .name # keep",
+                strip_comment_lines("# names\n.items[]\n  # of items\n| .name # keep\n\n")
+            );
+        }
+    }
+
+    mod deserialize_json {
+        use super::*;
+
+        #[test]
+        fn test_concatenated_values() {
+            let json_str = r#"{"a": 1}
+{
+  "b": [
+    2,
+    3
+  ]
+}{"c":null}[4]
+  "d" 5
+"#;
+            assert_eq!(
+                vec![
+                    serde_json::json!({"a": 1}),
+                    serde_json::json!({"b": [2, 3]}),
+                    serde_json::json!({"c": null}),
+                    serde_json::json!([4]),
+                    serde_json::json!("d"),
+                    serde_json::json!(5),
+                ],
+                deserialize_json(json_str, None, 128).unwrap().0
+            );
+        }
+
+        #[test]
+        fn test_limit_length() {
+            assert_eq!(
+                (vec![serde_json::json!(1), serde_json::json!(2)], true),
+                deserialize_json("1 2 3", Some(2), 128).unwrap()
+            );
+            assert_eq!(
+                (vec![serde_json::json!(1), serde_json::json!(2)], false),
+                deserialize_json("1 2\n", Some(2), 128).unwrap()
+            );
+        }
+
+        #[test]
+        fn test_invalid_value_after_valid_ones() {
+            assert!(deserialize_json("{\"a\": 1}\n{\"b\":", None, 128).is_err());
+        }
+
+        #[test]
+        fn test_max_depth() {
+            assert!(deserialize_json("[[1]]", None, 2).is_ok());
+            assert!(deserialize_json("[[[1]]]", None, 2).is_err());
+            let nested = |depth| format!("{}{}", "[".repeat(depth), "]".repeat(depth));
+            assert!(deserialize_json(&nested(128), None, 128).is_ok());
+            assert!(deserialize_json(&nested(129), None, 128).is_err());
+            assert!(deserialize_json(&nested(160), None, 160).is_ok());
+            assert!(deserialize_json(&nested(161), None, 160).is_err());
+        }
+    }
+
+    mod deserialize_ndjson {
+        use super::*;
+
+        #[test]
+        fn test_lines() {
+            assert_eq!(
+                vec![
+                    serde_json::json!({"a": 1}),
+                    serde_json::json!([2]),
+                    serde_json::json!("c"),
+                ],
+                deserialize_ndjson("{\"a\": 1}\n\n[2]\n\"c\"\n", None, 128).0
+            );
+        }
+
+        #[test]
+        fn test_limit_length() {
+            assert_eq!(
+                (vec![serde_json::json!(1), serde_json::json!(2)], true),
+                deserialize_ndjson("1\n2\n3", Some(2), 128)
+            );
+            assert_eq!(
+                (vec![serde_json::json!(1), serde_json::json!(2)], false),
+                deserialize_ndjson("1\n2\n\n", Some(2), 128)
+            );
+        }
+
+        #[test]
+        fn test_malformed_line() {
+            let (values, _) = deserialize_ndjson("{\"a\": 1}\n{\"b\":\n[[1]]", None, 1);
+            assert_eq!(3, values.len());
+            assert_eq!(serde_json::json!({"a": 1}), values[0]);
+            assert_eq!(serde_json::json!(2), values[1]["line"]);
+            assert_eq!(serde_json::json!("{\"b\":"), values[1]["text"]);
+            assert!(values[1]["error"].is_string());
+            assert_eq!(serde_json::json!(3), values[2]["line"]);
+        }
+    }
+}
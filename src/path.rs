@@ -0,0 +1,322 @@
+use std::iter;
+
+use promkit::{
+    json::{JsonNode, JsonPath, JsonPathSegment},
+    serde_json,
+};
+
+/// Converts a JSON path into the equivalent jq filter,
+/// e.g. `[Key("items"), Index(3), Key("name")]` becomes `.items[3].name`.
+/// Keys containing characters that are not valid in a jq identifier are quoted.
+pub fn to_query(segments: &[JsonPathSegment]) -> String {
+    if segments.is_empty() {
+        return ".".to_string();
+    }
+    segments
+        .iter()
+        .enumerate()
+        .map(|(i, segment)| match segment {
+            JsonPathSegment::Key(key) => {
+                if key.contains('.') || key.contains('-') || key.contains('@') {
+                    format!(".\"{}\"", key)
+                } else {
+                    format!(".{}", key)
+                }
+            }
+            JsonPathSegment::Index(index) => {
+                if i == 0 {
+                    format!(".[{}]", index)
+                } else {
+                    format!("[{}]", index)
+                }
+            }
+        })
+        .collect::<String>()
+}
+
+/// Parses a jq filter consisting only of object identifier-indexes and array indexes
+/// (the same subset that auto-completion produces) into a JSON path.
+/// Returns `None` if the filter uses any other jq syntax.
+pub fn parse_query(query: &str) -> Option<JsonPath> {
+    let chars = query.trim().chars().collect::<Vec<_>>();
+    if chars.first() != Some(&'.') {
+        return None;
+    }
+
+    let mut ret = JsonPath::new();
+    let mut i = 0;
+    while i < chars.len() {
+        match chars[i] {
+            '.' => {
+                i += 1;
+                match chars.get(i) {
+                    None | Some('[') => {}
+                    Some('"') => {
+                        let end = chars[i + 1..].iter().position(|c| *c == '"')? + i + 1;
+                        ret.push(JsonPathSegment::Key(chars[i + 1..end].iter().collect()));
+                        i = end + 1;
+                    }
+                    Some(_) => {
+                        let end = chars[i..]
+                            .iter()
+                            .position(|c| matches!(c, '.' | '['))
+                            .map_or(chars.len(), |p| p + i);
+                        let key = chars[i..end].iter().collect::<String>();
+                        if !key.chars().all(|c| c.is_alphanumeric() || c == '_') {
+                            return None;
+                        }
+                        ret.push(JsonPathSegment::Key(key));
+                        i = end;
+                    }
+                }
+            }
+            '[' => {
+                let end = chars[i + 1..].iter().position(|c| *c == ']')? + i + 1;
+                let index = chars[i + 1..end]
+                    .iter()
+                    .collect::<String>()
+                    .parse::<usize>()
+                    .ok()?;
+                ret.push(JsonPathSegment::Index(index));
+                i = end + 1;
+            }
+            _ => return None,
+        }
+    }
+    Some(ret)
+}
+
+/// Splits a JSON Pointer (RFC 6901) such as `/items/0/name` into its reference tokens,
+/// unescaping `~1` to `/` and `~0` to `~`. Each token follows a `/`,
+/// so anything before the first one is ignored.
+/// Returns the index of the first token with an invalid escape on failure.
+pub fn parse_pointer(pointer: &str) -> Result<Vec<String>, usize> {
+    pointer
+        .split('/')
+        .skip(1)
+        .enumerate()
+        .map(|(i, token)| {
+            let mut ret = String::new();
+            let mut chars = token.chars();
+            while let Some(c) = chars.next() {
+                ret.push(match c {
+                    '~' => match chars.next() {
+                        Some('0') => '~',
+                        Some('1') => '/',
+                        _ => return Err(i),
+                    },
+                    c => c,
+                });
+            }
+            Ok(ret)
+        })
+        .collect()
+}
+
+/// Resolves the reference tokens of a JSON Pointer against `node` into a JSON path.
+/// Whether a token is an array index or an object key depends on the node it applies to,
+/// since a pointer does not tell them apart (e.g. `/0` may be either).
+/// Returns the index of the first token that does not resolve on failure.
+pub fn resolve_pointer(node: &JsonNode, tokens: &[String]) -> Result<JsonPath, usize> {
+    let mut ret = JsonPath::new();
+    let mut node = node;
+    for (i, token) in tokens.iter().enumerate() {
+        let (segment, child) = match node {
+            JsonNode::Object { children, .. } => {
+                (JsonPathSegment::Key(token.clone()), children.get(token))
+            }
+            // Indexes have no sign and no leading zeros.
+            JsonNode::Array { children, .. } if token == "0" || !token.starts_with(['0', '+']) => {
+                let index = token.parse::<usize>().map_err(|_| i)?;
+                (JsonPathSegment::Index(index), children.get(index))
+            }
+            _ => return Err(i),
+        };
+        node = child.ok_or(i)?;
+        ret.push(segment);
+    }
+    Ok(ret)
+}
+
+/// Returns the path of every node within `roots` in document order,
+/// like the JSON viewer lists them fully expanded.
+/// Paths are produced as they are walked, so taking only some of them
+/// does not visit the rest of the data.
+pub fn paths(roots: &[serde_json::Value]) -> impl Iterator<Item = JsonPath> + '_ {
+    let mut stack = roots
+        .iter()
+        .rev()
+        .map(|root| (JsonPath::new(), root))
+        .collect::<Vec<_>>();
+    iter::from_fn(move || {
+        let (path, value) = stack.pop()?;
+        match value {
+            serde_json::Value::Object(map) => stack.extend(map.iter().rev().map(|(key, child)| {
+                let mut path = path.clone();
+                path.push(JsonPathSegment::Key(key.clone()));
+                (path, child)
+            })),
+            serde_json::Value::Array(children) => {
+                stack.extend(children.iter().enumerate().rev().map(|(index, child)| {
+                    let mut path = path.clone();
+                    path.push(JsonPathSegment::Index(index));
+                    (path, child)
+                }))
+            }
+            _ => (),
+        }
+        Some(path)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    mod to_query {
+        use super::*;
+
+        #[test]
+        fn test_root() {
+            assert_eq!(".", to_query(&[]));
+        }
+
+        #[test]
+        fn test_keys_and_indexes() {
+            assert_eq!(
+                ".items[3].name",
+                to_query(&[
+                    JsonPathSegment::Key("items".to_string()),
+                    JsonPathSegment::Index(3),
+                    JsonPathSegment::Key("name".to_string()),
+                ])
+            );
+        }
+
+        #[test]
+        fn test_leading_index_and_quoted_key() {
+            assert_eq!(
+                ".[0].\"a-b\"",
+                to_query(&[
+                    JsonPathSegment::Index(0),
+                    JsonPathSegment::Key("a-b".to_string()),
+                ])
+            );
+        }
+    }
+
+    mod parse_query {
+        use super::*;
+
+        #[test]
+        fn test_root() {
+            assert_eq!(Some(vec![]), parse_query("."));
+        }
+
+        #[test]
+        fn test_round_trip() {
+            for query in [".items[3].name", ".[0].\"a-b\"", ".a[1][2]"] {
+                assert_eq!(query, to_query(&parse_query(query).unwrap()));
+            }
+        }
+
+        #[test]
+        fn test_trailing_dot() {
+            assert_eq!(
+                Some(vec![JsonPathSegment::Key("items".to_string())]),
+                parse_query(".items.")
+            );
+        }
+
+        #[test]
+        fn test_not_a_path() {
+            assert_eq!(None, parse_query(".items[] | .name"));
+            assert_eq!(None, parse_query("keys"));
+            assert_eq!(None, parse_query(".items[-1]"));
+        }
+    }
+    mod parse_pointer {
+        use super::*;
+
+        #[test]
+        fn test_tokens() {
+            assert_eq!(Ok(vec![]), parse_pointer(""));
+            assert_eq!(Ok(vec!["".to_string()]), parse_pointer("/"));
+            assert_eq!(
+                Ok(vec!["items".to_string(), "0".to_string()]),
+                parse_pointer("/items/0")
+            );
+        }
+
+        #[test]
+        fn test_escapes() {
+            assert_eq!(Ok(vec!["a/b~c".to_string()]), parse_pointer("/a~1b~0c"));
+            assert_eq!(Err(1), parse_pointer("/a/b~2"));
+            assert_eq!(Err(0), parse_pointer("/a~"));
+        }
+    }
+
+    mod resolve_pointer {
+        use super::*;
+        use promkit::serde_json;
+
+        fn node() -> JsonNode {
+            JsonNode::new(
+                serde_json::json!({"items": [{"name": "a"}], "0": true}),
+                None,
+            )
+        }
+
+        fn tokens(pointer: &str) -> Vec<String> {
+            parse_pointer(pointer).unwrap()
+        }
+
+        #[test]
+        fn test_keys_and_indexes() {
+            assert_eq!(
+                Ok(".items[0].name".to_string()),
+                resolve_pointer(&node(), &tokens("/items/0/name")).map(|path| to_query(&path))
+            );
+            assert_eq!(
+                Ok(vec![JsonPathSegment::Key("0".to_string())]),
+                resolve_pointer(&node(), &tokens("/0"))
+            );
+        }
+
+        #[test]
+        fn test_unresolved_token() {
+            assert_eq!(Err(1), resolve_pointer(&node(), &tokens("/items/1/name")));
+            assert_eq!(Err(1), resolve_pointer(&node(), &tokens("/items/00")));
+            assert_eq!(Err(2), resolve_pointer(&node(), &tokens("/items/0/id")));
+            assert_eq!(Err(3), resolve_pointer(&node(), &tokens("/items/0/name/x")));
+        }
+    }
+
+    mod paths {
+        use super::*;
+
+        #[test]
+        fn test() {
+            let roots = [
+                serde_json::json!({"items": [{"name": "a"}, 1], "ok": true}),
+                serde_json::json!(2),
+                serde_json::json!([]),
+            ];
+            assert_eq!(
+                vec![
+                    ".",
+                    ".items",
+                    ".items[0]",
+                    ".items[0].name",
+                    ".items[1]",
+                    ".ok",
+                    ".",
+                    "."
+                ],
+                paths(&roots)
+                    .map(|path| to_query(&path))
+                    .collect::<Vec<_>>()
+            );
+        }
+    }
+}
@@ -0,0 +1,43 @@
+use std::{
+    io::{self, Write},
+    process::{Command, Output, Stdio},
+    thread,
+};
+
+/// Runs `command` with `input` on its standard input and returns what it printed
+/// along with its exit status, like `Command::output` does without input.
+pub fn pipe_through(command: &mut Command, input: Vec<u8>) -> io::Result<Output> {
+    let mut child = command
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()?;
+
+    // Write on another thread so that a large input cannot block on a full stdout pipe.
+    let mut stdin = child.stdin.take().expect("stdin is piped");
+    let writer = thread::spawn(move || stdin.write_all(&input));
+
+    let output = child.wait_with_output()?;
+    // The command may exit before reading all of its input, e.g. when it fails,
+    // which its exit status tells.
+    let _ = writer.join();
+    Ok(output)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    mod pipe_through {
+        use super::*;
+
+        #[cfg(unix)]
+        #[test]
+        fn test_large_input() {
+            let input = vec![b'a'; 1 << 20];
+            let output = pipe_through(&mut Command::new("cat"), input.clone()).unwrap();
+            assert!(output.status.success());
+            assert_eq!(input, output.stdout);
+        }
+    }
+}
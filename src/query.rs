@@ -0,0 +1,387 @@
+use std::ops::Range;
+
+/// Splits a jq filter at its top-level pipes,
+/// e.g. `.items[] | {name: (.a | .b)}` becomes `[".items[] ", " {name: (.a | .b)}"]`.
+/// Pipes within brackets or string literals and update-assignments (`|=`) are not split.
+pub fn split_pipes(query: &str) -> Vec<&str> {
+    let mut ret = Vec::new();
+    let mut depth = 0usize;
+    let mut in_string = false;
+    let mut escaped = false;
+    let mut start = 0;
+
+    let mut chars = query.char_indices().peekable();
+    while let Some((i, c)) = chars.next() {
+        if in_string {
+            match c {
+                _ if escaped => escaped = false,
+                '\\' => escaped = true,
+                '"' => in_string = false,
+                _ => {}
+            }
+            continue;
+        }
+        match c {
+            '"' => in_string = true,
+            '(' | '[' | '{' => depth += 1,
+            ')' | ']' | '}' => depth = depth.saturating_sub(1),
+            '|' if depth == 0 && chars.peek().map(|(_, next)| *next) != Some('=') => {
+                ret.push(&query[start..i]);
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    ret.push(&query[start..]);
+    ret
+}
+
+/// Returns the text to insert after `before_cursor` (the filter up to the cursor)
+/// so that `path` continues the filter as valid jq.
+/// The path is piped into unless it starts the filter or follows a pipe,
+/// an opening parenthesis, a comma or a semicolon.
+pub fn path_insertion(before_cursor: &str, path: &str) -> String {
+    let trimmed = before_cursor.trim_end();
+    let space = if before_cursor.len() > trimmed.len() || trimmed.ends_with('(') {
+        ""
+    } else {
+        " "
+    };
+    match trimmed.chars().last() {
+        None | Some('(') => path.to_string(),
+        Some('|' | ',' | ';') => format!("{}{}", space, path),
+        Some(_) => format!("{}| {}", space, path),
+    }
+}
+
+/// Returns the index (in chars) of the bracket matching the one at `position` in `query`,
+/// e.g. 6 for the `(` at 3 in `map(.a)`. Brackets within string literals are ignored,
+/// as are those left unmatched or closed by the wrong kind of bracket.
+pub fn matching_bracket(query: &str, position: usize) -> Option<usize> {
+    let mut open = Vec::new();
+    let mut in_string = false;
+    let mut escaped = false;
+
+    for (i, c) in query.chars().enumerate() {
+        if in_string {
+            match c {
+                _ if escaped => escaped = false,
+                '\\' => escaped = true,
+                '"' => in_string = false,
+                _ => {}
+            }
+            continue;
+        }
+        match c {
+            '"' => in_string = true,
+            '(' | '[' | '{' => open.push((i, c)),
+            ')' | ']' | '}' => {
+                let pair = match c {
+                    ')' => '(',
+                    ']' => '[',
+                    _ => '{',
+                };
+                if open.last().is_some_and(|&(_, last)| last == pair) {
+                    let (start, _) = open.pop()?;
+                    if start == position {
+                        return Some(i);
+                    }
+                    if i == position {
+                        return Some(start);
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+    None
+}
+
+/// Kinds of the parts of a filter highlighted in the filter editor.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum TokenKind {
+    Pipe,
+    String,
+    Field,
+    Builtin,
+}
+
+/// Names of the jq built-in functions highlighted in the filter editor.
+const BUILTINS: &[&str] = &[
+    "add",
+    "all",
+    "any",
+    "arrays",
+    "ascii_downcase",
+    "ascii_upcase",
+    "booleans",
+    "capture",
+    "combinations",
+    "contains",
+    "debug",
+    "del",
+    "delpaths",
+    "empty",
+    "endswith",
+    "env",
+    "error",
+    "explode",
+    "first",
+    "flatten",
+    "floor",
+    "from_entries",
+    "fromjson",
+    "getpath",
+    "group_by",
+    "gsub",
+    "has",
+    "implode",
+    "in",
+    "index",
+    "indices",
+    "input",
+    "inputs",
+    "inside",
+    "isempty",
+    "iterables",
+    "join",
+    "keys",
+    "keys_unsorted",
+    "last",
+    "leaf_paths",
+    "length",
+    "limit",
+    "ltrimstr",
+    "map",
+    "map_values",
+    "match",
+    "max",
+    "max_by",
+    "min",
+    "min_by",
+    "not",
+    "nth",
+    "nulls",
+    "numbers",
+    "objects",
+    "path",
+    "paths",
+    "range",
+    "recurse",
+    "reverse",
+    "rtrimstr",
+    "scalars",
+    "scan",
+    "select",
+    "setpath",
+    "sort",
+    "sort_by",
+    "split",
+    "splits",
+    "sqrt",
+    "startswith",
+    "strings",
+    "sub",
+    "test",
+    "to_entries",
+    "tojson",
+    "tonumber",
+    "tostring",
+    "transpose",
+    "type",
+    "unique",
+    "unique_by",
+    "until",
+    "values",
+    "walk",
+    "with_entries",
+];
+
+/// Splits `query` into the parts to highlight in the filter editor, as ranges of chars:
+/// top-level and nested pipes, string literals, field accessors (e.g. `.name`),
+/// built-in function names and formats (e.g. `@csv`). Anything else is left out,
+/// so a half-typed filter (e.g. an unterminated string) still highlights what it can.
+pub fn tokenize(query: &str) -> Vec<(TokenKind, Range<usize>)> {
+    let chars = query.chars().collect::<Vec<_>>();
+    let is_ident = |c: char| c.is_ascii_alphanumeric() || c == '_';
+    let mut ret = Vec::new();
+
+    let mut i = 0;
+    while i < chars.len() {
+        let start = i;
+        match chars[i] {
+            '"' => {
+                i += 1;
+                let mut escaped = false;
+                while i < chars.len() {
+                    let c = chars[i];
+                    i += 1;
+                    match c {
+                        _ if escaped => escaped = false,
+                        '\\' => escaped = true,
+                        '"' => break,
+                        _ => {}
+                    }
+                }
+                ret.push((TokenKind::String, start..i));
+                continue;
+            }
+            '|' if chars.get(i + 1) != Some(&'=') => ret.push((TokenKind::Pipe, i..i + 1)),
+            '.' | '@'
+                if chars
+                    .get(i + 1)
+                    .is_some_and(|&c| is_ident(c) && !c.is_ascii_digit()) =>
+            {
+                i += 1;
+                while i < chars.len() && is_ident(chars[i]) {
+                    i += 1;
+                }
+                let kind = match chars[start] {
+                    '.' => TokenKind::Field,
+                    _ => TokenKind::Builtin,
+                };
+                ret.push((kind, start..i));
+                continue;
+            }
+            c if is_ident(c) => {
+                while i < chars.len() && is_ident(chars[i]) {
+                    i += 1;
+                }
+                // Variables such as `$length` are not calls to the built-in.
+                let name = chars[start..i].iter().collect::<String>();
+                if (start == 0 || chars[start - 1] != '$') && BUILTINS.contains(&name.as_str()) {
+                    ret.push((TokenKind::Builtin, start..i));
+                }
+                continue;
+            }
+            _ => {}
+        }
+        i += 1;
+    }
+    ret
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    mod split_pipes {
+        use super::*;
+
+        #[test]
+        fn test_without_pipe() {
+            assert_eq!(vec![".a.b"], split_pipes(".a.b"));
+        }
+
+        #[test]
+        fn test_top_level_pipes() {
+            assert_eq!(
+                vec![".items[] ", " .name ", " length"],
+                split_pipes(".items[] | .name | length")
+            );
+        }
+
+        #[test]
+        fn test_nested_pipes_and_strings() {
+            assert_eq!(
+                vec![".a ", " {b: (.c | .d), e: \"f|g\\\"|\"}"],
+                split_pipes(".a | {b: (.c | .d), e: \"f|g\\\"|\"}")
+            );
+        }
+
+        #[test]
+        fn test_update_assignment() {
+            assert_eq!(vec![".a |= 1 ", " .a"], split_pipes(".a |= 1 | .a"));
+        }
+    }
+
+    mod path_insertion {
+        use super::*;
+
+        #[test]
+        fn test_start() {
+            assert_eq!(".a[0]", path_insertion("", ".a[0]"));
+            assert_eq!(".a[0]", path_insertion("map(", ".a[0]"));
+        }
+
+        #[test]
+        fn test_after_pipe() {
+            assert_eq!(" .a", path_insertion(".items |", ".a"));
+            assert_eq!(".a", path_insertion(".items | ", ".a"));
+            assert_eq!(" .a", path_insertion("[.b,", ".a"));
+        }
+
+        #[test]
+        fn test_piped() {
+            assert_eq!(" | .[0].name", path_insertion(".items", ".[0].name"));
+            assert_eq!("| .[0].name", path_insertion(".items ", ".[0].name"));
+        }
+    }
+
+    mod matching_bracket {
+        use super::*;
+
+        #[test]
+        fn test_nested() {
+            let query = "map({a: .b[0]})";
+            assert_eq!(Some(14), matching_bracket(query, 3));
+            assert_eq!(Some(3), matching_bracket(query, 14));
+            assert_eq!(Some(13), matching_bracket(query, 4));
+            assert_eq!(Some(10), matching_bracket(query, 12));
+        }
+
+        #[test]
+        fn test_not_on_bracket() {
+            assert_eq!(None, matching_bracket("map(.a)", 0));
+            assert_eq!(None, matching_bracket("map(.a)", 7));
+        }
+
+        #[test]
+        fn test_strings_and_unmatched() {
+            assert_eq!(Some(8), matching_bracket(r#"("a)\"(")"#, 0));
+            assert_eq!(None, matching_bracket("(.a]", 0));
+            assert_eq!(None, matching_bracket("map(.a", 3));
+        }
+    }
+
+    mod tokenize {
+        use super::*;
+
+        #[test]
+        fn test() {
+            assert_eq!(
+                vec![
+                    (TokenKind::Field, 0..6),
+                    (TokenKind::Pipe, 9..10),
+                    (TokenKind::Builtin, 11..17),
+                    (TokenKind::Field, 18..23),
+                    (TokenKind::String, 27..32),
+                    (TokenKind::Pipe, 34..35),
+                    (TokenKind::Builtin, 36..40),
+                ],
+                tokenize(r#".items[] | select(.name == "a|b") | @csv"#)
+            );
+        }
+
+        #[test]
+        fn test_not_highlighted() {
+            assert_eq!(
+                vec![
+                    (TokenKind::Pipe, 8..9),
+                    (TokenKind::Field, 10..12),
+                    (TokenKind::Pipe, 18..19),
+                    (TokenKind::Pipe, 25..26),
+                ],
+                tokenize("$length | .a |= 1 | .[0] | lengthy")
+            );
+        }
+
+        #[test]
+        fn test_unterminated_string() {
+            assert_eq!(
+                vec![(TokenKind::Field, 0..2), (TokenKind::String, 3..7)],
+                tokenize(r#".a "b\""#)
+            );
+        }
+    }
+}
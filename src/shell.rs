@@ -0,0 +1,40 @@
+/// Quotes `arg` for POSIX shells, leaving it as is when no quoting is needed,
+/// e.g. `.a | .b` becomes `'.a | .b'` and `it's` becomes `'it'\''s'`.
+pub fn quote(arg: &str) -> String {
+    let is_safe = !arg.is_empty()
+        && arg
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || "-_./=:,+@%".contains(c));
+    if is_safe {
+        arg.to_string()
+    } else {
+        format!("'{}'", arg.replace('\'', r"'\''"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    mod quote {
+        use super::*;
+
+        #[test]
+        fn test_safe() {
+            assert_eq!("data/a.json", quote("data/a.json"));
+            assert_eq!("--indent", quote("--indent"));
+        }
+
+        #[test]
+        fn test_quoted() {
+            assert_eq!("'.a | .b'", quote(".a | .b"));
+            assert_eq!("'.[\"a b\"]'", quote(".[\"a b\"]"));
+            assert_eq!("''", quote(""));
+        }
+
+        #[test]
+        fn test_single_quote() {
+            assert_eq!(r"'it'\''s $HOME'", quote("it's $HOME"));
+        }
+    }
+}
@@ -0,0 +1,128 @@
+use std::{
+    env, fs,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{anyhow, Result};
+use promkit::serde_json;
+
+/// Name of the file in the state directory mapping input files to their last query.
+const LAST_QUERIES_FILE: &str = "last_queries.json";
+/// Name of the file in the state directory listing the pinned queries.
+const PINNED_QUERIES_FILE: &str = "pinned_queries.json";
+
+/// Returns the directory where jnv keeps its state, given the values of
+/// `XDG_STATE_HOME` and `HOME`: `$XDG_STATE_HOME/jnv`, or `~/.local/state/jnv`.
+pub fn state_dir(xdg_state_home: Option<&str>, home: Option<&str>) -> Option<PathBuf> {
+    match (xdg_state_home, home) {
+        (Some(dir), _) if !dir.is_empty() => Some(PathBuf::from(dir).join("jnv")),
+        (_, Some(home)) if !home.is_empty() => Some(PathBuf::from(home).join(".local/state/jnv")),
+        _ => None,
+    }
+}
+
+fn state_file(name: &str) -> Result<PathBuf> {
+    state_dir(
+        env::var("XDG_STATE_HOME").ok().as_deref(),
+        env::var("HOME").ok().as_deref(),
+    )
+    .map(|dir| dir.join(name))
+    .ok_or_else(|| anyhow!("Neither XDG_STATE_HOME nor HOME is set"))
+}
+
+fn write_state_file(path: &Path, json: &serde_json::Value) -> Result<()> {
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    fs::write(path, serde_json::to_string_pretty(json)?)?;
+    Ok(())
+}
+
+fn read_last_queries(path: &Path) -> serde_json::Map<String, serde_json::Value> {
+    fs::read_to_string(path)
+        .ok()
+        .and_then(|json| serde_json::from_str(&json).ok())
+        .unwrap_or_default()
+}
+
+/// Returns the key of the input file at `input` in the saved queries: its absolute path.
+fn key(input: &Path) -> String {
+    fs::canonicalize(input)
+        .unwrap_or_else(|_| input.to_path_buf())
+        .to_string_lossy()
+        .to_string()
+}
+
+/// Returns the last successful query saved for the input file at `input`, if any.
+pub fn load_last_query(input: &Path) -> Option<String> {
+    read_last_queries(&state_file(LAST_QUERIES_FILE).ok()?)
+        .get(&key(input))
+        .and_then(|query| query.as_str())
+        .map(String::from)
+}
+
+/// Returns the last successful queries saved for every input file.
+pub fn load_last_queries() -> Vec<String> {
+    let Ok(path) = state_file(LAST_QUERIES_FILE) else {
+        return Vec::new();
+    };
+    read_last_queries(&path)
+        .into_iter()
+        .filter_map(|(_, query)| query.as_str().map(String::from))
+        .collect()
+}
+
+/// Saves `query` as the last successful query for the input file at `input`.
+pub fn save_last_query(input: &Path, query: &str) -> Result<()> {
+    let path = state_file(LAST_QUERIES_FILE)?;
+    let mut queries = read_last_queries(&path);
+    queries.insert(key(input), serde_json::Value::String(query.to_string()));
+    write_state_file(&path, &serde_json::Value::Object(queries))
+}
+
+/// Returns the pinned queries saved by [`save_pinned_queries`], oldest first.
+pub fn load_pinned_queries() -> Vec<String> {
+    state_file(PINNED_QUERIES_FILE)
+        .ok()
+        .and_then(|path| fs::read_to_string(path).ok())
+        .and_then(|json| serde_json::from_str(&json).ok())
+        .unwrap_or_default()
+}
+
+/// Saves `queries` as the pinned queries, replacing those saved before.
+pub fn save_pinned_queries(queries: &[String]) -> Result<()> {
+    write_state_file(
+        &state_file(PINNED_QUERIES_FILE)?,
+        &serde_json::Value::from(queries),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    mod state_dir {
+        use super::*;
+
+        #[test]
+        fn test_xdg_state_home() {
+            assert_eq!(
+                Some(PathBuf::from("/state/jnv")),
+                state_dir(Some("/state"), Some("/home/u"))
+            );
+        }
+
+        #[test]
+        fn test_home() {
+            assert_eq!(
+                Some(PathBuf::from("/home/u/.local/state/jnv")),
+                state_dir(Some(""), Some("/home/u"))
+            );
+        }
+
+        #[test]
+        fn test_none() {
+            assert_eq!(None, state_dir(None, None));
+        }
+    }
+}